@@ -1,3 +1,5 @@
+use std::f32::consts::PI;
+
 use image::buffer::ConvertBuffer;
 use image::{GrayImage, RgbaImage};
 use image_processing::blur::{box_blur_mut, BoxKernel};
@@ -5,7 +7,14 @@ use image_processing::edge_detection::sobel_mut;
 use image_processing::pixel_ops::invert_mut;
 use image_processing::pixel_ops::power_law_transform_mut;
 
+use crate::fft::{fft_2d, shifted_index, Complex};
+use crate::{
+    Corner, CornerDetector, CornerMethod, FilterShape, FrequencyBand, FrequencyFilter, Histogram,
+};
+
 const CHANNEL_COUNT: u32 = 4;
+/// rec. 709 weights of the red, green and blue channels in the luminance
+const LUMINANCE_WEIGHTS: [f32; 3] = [0.2126, 0.7152, 0.0722];
 
 pub fn invert(input_image: Vec<u8>, width: u32) -> Vec<u8> {
     let height = (input_image.len() as u32 / CHANNEL_COUNT) / width;
@@ -53,3 +62,1176 @@ pub fn sobel_edge_detection(input_image: Vec<u8>, width: u32, threshold: u8) ->
 
     image.into_vec()
 }
+
+/// smooths the luminance with a gaussian, keeps only the pixels where the gradient is strongest
+/// across the edge and then traces edges from pixels above the high threshold through pixels
+/// above the low threshold, the thresholds are on the gradient magnitude scaled so a hard black
+/// to white edge is 255
+pub fn canny_edge_detection(
+    input_image: Vec<u8>,
+    width: u32,
+    sigma: f32,
+    low_threshold: f32,
+    high_threshold: f32,
+) -> Vec<u8> {
+    let height = (input_image.len() as u32 / CHANNEL_COUNT) / width;
+    let (width, height) = (width as usize, height as usize);
+    let luminance = luminance(&input_image);
+    let smoothed = separable_convolution(&luminance, width, height, 1, &gaussian_kernel(sigma));
+
+    // the sobel kernel weights add up to 4 on each side
+    let gradients = sobel_gradients(&smoothed, width, height);
+    let magnitude = |x: isize, y: isize| {
+        if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+            return 0.;
+        }
+        let (gradient_x, gradient_y) = gradients[y as usize * width + x as usize];
+        gradient_x.hypot(gradient_y) / 4.
+    };
+
+    // non-maximum suppression against the two neighbours along the gradient, the direction is
+    // rounded to a multiple of 45 degrees
+    let mut thinned = vec![0.; width * height];
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let (gradient_x, gradient_y) = gradients[y as usize * width + x as usize];
+            let angle = gradient_y.atan2(gradient_x).to_degrees().rem_euclid(180.);
+            let (step_x, step_y) = match angle {
+                angle if !(22.5..157.5).contains(&angle) => (1, 0),
+                angle if angle < 67.5 => (1, 1),
+                angle if angle < 112.5 => (0, 1),
+                _ => (-1, 1),
+            };
+            // ties are only won against the neighbour before so a plateau two pixels wide
+            // still leaves a single pixel
+            let value = magnitude(x, y);
+            if value > magnitude(x + step_x, y + step_y)
+                && value >= magnitude(x - step_x, y - step_y)
+            {
+                thinned[y as usize * width + x as usize] = value;
+            }
+        }
+    }
+
+    // hysteresis, weak pixels only become edges when they connect to a strong one
+    let mut is_edge = vec![false; width * height];
+    let mut stack = (0..width * height)
+        .filter(|&index| thinned[index] >= high_threshold)
+        .collect::<Vec<_>>();
+    for &index in &stack {
+        is_edge[index] = true;
+    }
+    while let Some(index) = stack.pop() {
+        let (x, y) = (index % width, index / width);
+        for neighbour_y in y.saturating_sub(1)..(y + 2).min(height) {
+            for neighbour_x in x.saturating_sub(1)..(x + 2).min(width) {
+                let neighbour = neighbour_y * width + neighbour_x;
+                if !is_edge[neighbour] && thinned[neighbour] >= low_threshold {
+                    is_edge[neighbour] = true;
+                    stack.push(neighbour);
+                }
+            }
+        }
+    }
+
+    binarize(input_image, &luminance, |index, _| is_edge[index])
+}
+
+/// the horizontal and vertical sobel gradients of every sample with the edges clamped
+fn sobel_gradients(samples: &[f32], width: usize, height: usize) -> Vec<(f32, f32)> {
+    let sample = |x: isize, y: isize| {
+        let x = x.clamp(0, width as isize - 1) as usize;
+        let y = y.clamp(0, height as isize - 1) as usize;
+        samples[y * width + x]
+    };
+    let mut gradients = Vec::with_capacity(width * height);
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let gradient_x = (sample(x + 1, y - 1) + 2. * sample(x + 1, y) + sample(x + 1, y + 1))
+                - (sample(x - 1, y - 1) + 2. * sample(x - 1, y) + sample(x - 1, y + 1));
+            let gradient_y = (sample(x - 1, y + 1) + 2. * sample(x, y + 1) + sample(x + 1, y + 1))
+                - (sample(x - 1, y - 1) + 2. * sample(x, y - 1) + sample(x + 1, y - 1));
+            gradients.push((gradient_x, gradient_y));
+        }
+    }
+
+    gradients
+}
+
+pub fn gaussian_blur(input_image: Vec<u8>, width: u32, sigma: f32) -> Vec<u8> {
+    let blurred = gaussian_blur_samples(&input_image, width, sigma);
+
+    let mut image = input_image;
+    for (pixel, blurred_pixel) in image
+        .chunks_exact_mut(CHANNEL_COUNT as usize)
+        .zip(blurred.chunks_exact(CHANNEL_COUNT as usize))
+    {
+        // alpha is left untouched
+        for (sample, blurred_sample) in pixel.iter_mut().zip(blurred_pixel).take(3) {
+            *sample = blurred_sample.round().clamp(0., 255.) as u8;
+        }
+    }
+
+    image
+}
+
+/// sharpens by adding back the difference between the image and a blurred copy of it
+/// differences smaller than the threshold are ignored so noise and smooth areas aren't sharpened
+pub fn unsharp_mask(
+    input_image: Vec<u8>,
+    width: u32,
+    amount: f32,
+    radius: f32,
+    threshold: u8,
+) -> Vec<u8> {
+    let blurred = gaussian_blur_samples(&input_image, width, radius);
+
+    let mut image = input_image;
+    for (pixel, blurred_pixel) in image
+        .chunks_exact_mut(CHANNEL_COUNT as usize)
+        .zip(blurred.chunks_exact(CHANNEL_COUNT as usize))
+    {
+        for (sample, blurred_sample) in pixel.iter_mut().zip(blurred_pixel).take(3) {
+            let difference = *sample as f32 - blurred_sample;
+            if difference.abs() >= threshold as f32 {
+                *sample = (*sample as f32 + amount * difference)
+                    .round()
+                    .clamp(0., 255.) as u8;
+            }
+        }
+    }
+
+    image
+}
+
+/// blurs every channel including alpha and returns the unrounded samples
+fn gaussian_blur_samples(image: &[u8], width: u32, sigma: f32) -> Vec<f32> {
+    let height = (image.len() as u32 / CHANNEL_COUNT) / width;
+    let samples = image
+        .iter()
+        .map(|&sample| sample as f32)
+        .collect::<Vec<_>>();
+
+    separable_convolution(
+        &samples,
+        width as usize,
+        height as usize,
+        CHANNEL_COUNT as usize,
+        &gaussian_kernel(sigma),
+    )
+}
+
+/// the radius of the kernel is 3 sigma which covers more than 99% of the gaussian
+pub fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+    let radius = (3. * sigma).ceil().max(1.) as i32;
+    let mut kernel = (-radius..=radius)
+        .map(|x| (-((x * x) as f32) / (2. * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|weight| *weight /= sum);
+
+    kernel
+}
+
+/// convolves every channel of the interleaved samples with the kernel horizontally then
+/// vertically, this is equivalent to a 2d kernel but much cheaper for large kernels
+/// edges are handled by clamping to the nearest pixel
+pub fn separable_convolution(
+    samples: &[f32],
+    width: usize,
+    height: usize,
+    channels: usize,
+    kernel: &[f32],
+) -> Vec<f32> {
+    let radius = (kernel.len() / 2) as isize;
+
+    let mut horizontal = vec![0.; samples.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let output = (y * width + x) * channels;
+            for (i, weight) in kernel.iter().enumerate() {
+                let sample_x = (x as isize + i as isize - radius).clamp(0, width as isize - 1);
+                let input = (y * width + sample_x as usize) * channels;
+                for channel in 0..channels {
+                    horizontal[output + channel] += weight * samples[input + channel];
+                }
+            }
+        }
+    }
+
+    let mut vertical = vec![0.; samples.len()];
+    for y in 0..height {
+        for (i, weight) in kernel.iter().enumerate() {
+            let sample_y = (y as isize + i as isize - radius).clamp(0, height as isize - 1);
+            let input_row = sample_y as usize * width * channels;
+            let output_row = y * width * channels;
+            for offset in 0..width * channels {
+                vertical[output_row + offset] += weight * horizontal[input_row + offset];
+            }
+        }
+    }
+
+    vertical
+}
+
+/// hue is in degrees and shifts the hue around the color wheel
+/// saturation and lightness go from -100 to 100 percent, -100 lightness is black and 100 is white
+pub fn hue_saturation_lightness(
+    input_image: Vec<u8>,
+    hue: f32,
+    saturation: f32,
+    lightness: f32,
+) -> Vec<u8> {
+    let saturation = saturation / 100.;
+    let lightness = lightness / 100.;
+
+    let mut image = input_image;
+    for pixel in image.chunks_exact_mut(CHANNEL_COUNT as usize) {
+        let (h, s, l) = rgb_to_hsl(pixel[0], pixel[1], pixel[2]);
+
+        let h = h + hue;
+        let s = (s * (1. + saturation)).clamp(0., 1.);
+        let l = if lightness > 0. {
+            l + (1. - l) * lightness
+        } else {
+            l + l * lightness
+        };
+
+        // alpha is left untouched
+        let (red, green, blue) = hsl_to_rgb(h, s, l);
+        pixel[0] = red;
+        pixel[1] = green;
+        pixel[2] = blue;
+    }
+
+    image
+}
+
+/// returns hue in degrees from 0 to 360 and saturation and lightness from 0 to 1
+pub fn rgb_to_hsl(red: u8, green: u8, blue: u8) -> (f32, f32, f32) {
+    let (hue, max, min) = hue_max_min(red, green, blue);
+    let lightness = (max + min) / 2.;
+    let chroma = max - min;
+    let saturation = if chroma == 0. {
+        0.
+    } else {
+        chroma / (1. - (2. * lightness - 1.).abs())
+    };
+
+    (hue, saturation.clamp(0., 1.), lightness)
+}
+
+pub fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> (u8, u8, u8) {
+    let lightness = lightness.clamp(0., 1.);
+    let chroma = (1. - (2. * lightness - 1.).abs()) * saturation.clamp(0., 1.);
+    chroma_to_rgb(hue, chroma, lightness - chroma / 2.)
+}
+
+/// returns hue in degrees from 0 to 360 and saturation and value from 0 to 1
+pub fn rgb_to_hsv(red: u8, green: u8, blue: u8) -> (f32, f32, f32) {
+    let (hue, max, min) = hue_max_min(red, green, blue);
+    let saturation = if max == 0. { 0. } else { (max - min) / max };
+
+    (hue, saturation, max)
+}
+
+pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (u8, u8, u8) {
+    let value = value.clamp(0., 1.);
+    let chroma = value * saturation.clamp(0., 1.);
+    chroma_to_rgb(hue, chroma, value - chroma)
+}
+
+fn hue_max_min(red: u8, green: u8, blue: u8) -> (f32, f32, f32) {
+    let red = red as f32 / 255.;
+    let green = green as f32 / 255.;
+    let blue = blue as f32 / 255.;
+
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let chroma = max - min;
+
+    let hue = if chroma == 0. {
+        0.
+    } else if max == red {
+        60. * ((green - blue) / chroma).rem_euclid(6.)
+    } else if max == green {
+        60. * ((blue - red) / chroma + 2.)
+    } else {
+        60. * ((red - green) / chroma + 4.)
+    };
+
+    (hue, max, min)
+}
+
+/// shared last step of the hsl and hsv conversions, m is added to every channel to match the
+/// lightness or value
+fn chroma_to_rgb(hue: f32, chroma: f32, m: f32) -> (u8, u8, u8) {
+    let hue = hue.rem_euclid(360.) / 60.;
+    let x = chroma * (1. - (hue.rem_euclid(2.) - 1.).abs());
+
+    let (red, green, blue) = match hue as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    let to_byte = |channel: f32| ((channel + m) * 255.).round().clamp(0., 255.) as u8;
+
+    (to_byte(red), to_byte(green), to_byte(blue))
+}
+
+/// where the highlight roll-off starts in linear light, values above this get compressed
+/// towards white instead of clipping
+const HIGHLIGHT_ROLLOFF_KNEE: f32 = 0.8;
+
+/// stops is the exposure value change, every stop doubles or halves the amount of light
+/// the scaling happens in linear light so it behaves like changing the exposure of a camera
+pub fn exposure(input_image: Vec<u8>, stops: f32, highlight_rolloff: bool) -> Vec<u8> {
+    let scale = 2f32.powf(stops);
+    let headroom = 1. - HIGHLIGHT_ROLLOFF_KNEE;
+    // the brightest possible input after scaling, the roll-off maps it to exactly white so only
+    // values that would have clipped get compressed
+    let white_point = (scale - HIGHLIGHT_ROLLOFF_KNEE) / headroom;
+
+    let lookup_table = (0..=u8::MAX)
+        .map(|value| {
+            let linear = srgb_to_linear(value) * scale;
+            let linear = if highlight_rolloff && scale > 1. && linear > HIGHLIGHT_ROLLOFF_KNEE {
+                // extended reinhard curve, it starts with a slope of 1 at the knee
+                let x = (linear - HIGHLIGHT_ROLLOFF_KNEE) / headroom;
+                let compressed = x * (1. + x / (white_point * white_point)) / (1. + x);
+                HIGHLIGHT_ROLLOFF_KNEE + headroom * compressed
+            } else {
+                linear
+            };
+            linear_to_srgb(linear)
+        })
+        .collect::<Vec<_>>();
+
+    let mut image = input_image;
+    for pixel in image.chunks_exact_mut(CHANNEL_COUNT as usize) {
+        // alpha is left untouched
+        for sample in pixel.iter_mut().take(3) {
+            *sample = lookup_table[*sample as usize];
+        }
+    }
+
+    image
+}
+
+/// converts an srgb encoded byte to linear light from 0 to 1
+pub fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.;
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// converts linear light to an srgb encoded byte, values outside of 0 to 1 are clipped
+pub fn linear_to_srgb(value: f32) -> u8 {
+    let value = value.clamp(0., 1.);
+    let value = if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1. / 2.4) - 0.055
+    };
+
+    (value * 255.).round() as u8
+}
+
+/// converts srgb to cie l*a*b* with the d65 white point, lightness goes from 0 to 100
+pub fn rgb_to_lab(red: u8, green: u8, blue: u8) -> (f32, f32, f32) {
+    let (red, green, blue) = (
+        srgb_to_linear(red),
+        srgb_to_linear(green),
+        srgb_to_linear(blue),
+    );
+    let x = (0.4124 * red + 0.3576 * green + 0.1805 * blue) / 0.95047;
+    let y = 0.2126 * red + 0.7152 * green + 0.0722 * blue;
+    let z = (0.0193 * red + 0.1192 * green + 0.9505 * blue) / 1.08883;
+
+    let f = |t: f32| {
+        let delta: f32 = 6. / 29.;
+        if t > delta.powi(3) {
+            t.cbrt()
+        } else {
+            t / (3. * delta * delta) + 4. / 29.
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    (116. * fy - 16., 500. * (fx - fy), 200. * (fy - fz))
+}
+
+/// the luminance of every pixel from 0 to 255 using the rec. 709 weights
+pub fn luminance(image: &[u8]) -> Vec<f32> {
+    image
+        .chunks_exact(CHANNEL_COUNT as usize)
+        .map(|pixel| {
+            pixel
+                .iter()
+                .zip(LUMINANCE_WEIGHTS)
+                .map(|(value, weight)| *value as f32 * weight)
+                .sum()
+        })
+        .collect()
+}
+
+/// counts the pixels with each value in every channel and in the rounded luminance, the alpha
+/// channel is ignored
+pub fn histogram(image: &[u8]) -> Histogram {
+    let mut histogram = Histogram {
+        red: vec![0; 256],
+        green: vec![0; 256],
+        blue: vec![0; 256],
+        luminance: vec![0; 256],
+    };
+    for (pixel, luminance) in image
+        .chunks_exact(CHANNEL_COUNT as usize)
+        .zip(luminance(image))
+    {
+        histogram.red[pixel[0] as usize] += 1;
+        histogram.green[pixel[1] as usize] += 1;
+        histogram.blue[pixel[2] as usize] += 1;
+        histogram.luminance[level_of(luminance)] += 1;
+    }
+
+    histogram
+}
+
+/// spreads the luminance over the whole range so every level is used about equally often, the
+/// change in luminance is added to every channel so the chroma and with it the colors stay the
+/// same
+pub fn equalize_histogram(input_image: Vec<u8>) -> Vec<u8> {
+    let luminance = luminance(&input_image);
+    let levels = luminance_histogram(&luminance);
+
+    let total = luminance.len() as f32;
+    let mut cumulative = 0;
+    let mut lookup_table = [0.; 256];
+    // the darkest level used maps to black, otherwise its whole share would be lost above zero
+    let darkest = levels.iter().copied().find(|&count| count > 0).unwrap_or(0) as f32;
+    for (level, count) in levels.iter().enumerate() {
+        cumulative += count;
+        lookup_table[level] = if total > darkest {
+            (cumulative as f32 - darkest) / (total - darkest) * 255.
+        } else {
+            // every pixel has the same level so there's nothing to spread
+            level as f32
+        };
+    }
+
+    shift_luminance(input_image, &luminance, |_, value| {
+        lookup_table[level_of(value)]
+    })
+}
+
+/// equalizes each tile of a grid separately and blends the mappings of the nearest tiles so the
+/// seams don't show, the histogram of every tile is clipped at clip limit times the average bin
+/// count so flat areas don't get their noise amplified
+pub fn clahe(input_image: Vec<u8>, width: u32, tile_grid_size: u32, clip_limit: f32) -> Vec<u8> {
+    let width = width as usize;
+    let height = input_image.len() / CHANNEL_COUNT as usize / width;
+    let luminance = luminance(&input_image);
+    // tiles need at least one pixel each
+    let tiles_x = (tile_grid_size as usize).clamp(1, width.max(1));
+    let tiles_y = (tile_grid_size as usize).clamp(1, height.max(1));
+    let tile_of = |position: usize, size: usize, tiles: usize| position * tiles / size;
+
+    let mut tiles = vec![Vec::new(); tiles_x * tiles_y];
+    for (index, value) in luminance.iter().enumerate() {
+        let tile = tile_of(index / width, height, tiles_y) * tiles_x
+            + tile_of(index % width, width, tiles_x);
+        tiles[tile].push(*value);
+    }
+
+    let lookup_tables = tiles
+        .iter()
+        .map(|tile| {
+            let histogram = clip_histogram(luminance_histogram(tile), clip_limit);
+            let total = histogram.iter().sum::<f32>().max(1.);
+
+            let mut cumulative = 0.;
+            let mut lookup_table = [0.; 256];
+            for (level, count) in histogram.iter().enumerate() {
+                cumulative += count;
+                lookup_table[level] = cumulative / total * 255.;
+            }
+            lookup_table
+        })
+        .collect::<Vec<_>>();
+
+    // the position of a pixel in tiles, measured from the center of the first tile
+    let tile_position = |position: usize, size: usize, tiles: usize| {
+        let position = ((position as f32 + 0.5) * tiles as f32 / size as f32 - 0.5)
+            .clamp(0., (tiles - 1) as f32);
+        let first = position as usize;
+        (first, (first + 1).min(tiles - 1), position - first as f32)
+    };
+
+    shift_luminance(input_image, &luminance, |index, value| {
+        let (left, right, x_fraction) = tile_position(index % width, width, tiles_x);
+        let (top, bottom, y_fraction) = tile_position(index / width, height, tiles_y);
+        let level = level_of(value);
+        let mapped = |x: usize, y: usize| lookup_tables[y * tiles_x + x][level];
+
+        let top_row = mapped(left, top) * (1. - x_fraction) + mapped(right, top) * x_fraction;
+        let bottom_row =
+            mapped(left, bottom) * (1. - x_fraction) + mapped(right, bottom) * x_fraction;
+        top_row * (1. - y_fraction) + bottom_row * y_fraction
+    })
+}
+
+/// caps every bin at clip limit times the average bin count and spreads what was cut off evenly
+/// over all bins, a limit of 1 flattens the histogram completely
+fn clip_histogram(histogram: [u32; 256], clip_limit: f32) -> [f32; 256] {
+    let limit = clip_limit * histogram.iter().sum::<u32>() as f32 / 256.;
+    let excess = histogram
+        .iter()
+        .map(|&count| (count as f32 - limit).max(0.))
+        .sum::<f32>();
+    histogram.map(|count| (count as f32).min(limit) + excess / 256.)
+}
+
+/// counts the pixels at each rounded luminance level
+fn luminance_histogram(luminance: &[f32]) -> [u32; 256] {
+    let mut histogram = [0; 256];
+    for value in luminance {
+        histogram[level_of(*value)] += 1;
+    }
+    histogram
+}
+
+fn level_of(luminance: f32) -> usize {
+    (luminance.round() as usize).min(255)
+}
+
+/// moves every pixel to the luminance returned for it by adding the same amount to each channel,
+/// the mapping gets the index of the pixel and its current luminance
+fn shift_luminance(
+    input_image: Vec<u8>,
+    luminance: &[f32],
+    mapping: impl Fn(usize, f32) -> f32,
+) -> Vec<u8> {
+    let mut image = input_image;
+    for (index, (pixel, value)) in image
+        .chunks_exact_mut(CHANNEL_COUNT as usize)
+        .zip(luminance)
+        .enumerate()
+    {
+        let shift = mapping(index, *value) - value;
+        // alpha is left untouched
+        for sample in pixel.iter_mut().take(3) {
+            *sample = (*sample as f32 + shift).round().clamp(0., 255.) as u8;
+        }
+    }
+
+    image
+}
+
+/// turns pixels with a luminance above the level white and the rest black, alpha is kept
+pub fn threshold(input_image: Vec<u8>, level: u8) -> Vec<u8> {
+    let luminance = luminance(&input_image);
+    binarize(input_image, &luminance, |_, value| value > level as f32)
+}
+
+/// the level that best splits the luminance into a dark and a bright class, found by maximizing
+/// the variance between the two classes like otsu's method does
+pub fn otsu_level(image: &[u8]) -> u8 {
+    let levels = luminance_histogram(&luminance(image));
+    let total = levels.iter().sum::<u32>() as f64;
+    let total_sum = levels
+        .iter()
+        .enumerate()
+        .map(|(level, count)| level as f64 * *count as f64)
+        .sum::<f64>();
+
+    let mut best = (0, 0.);
+    let mut dark_count = 0.;
+    let mut dark_sum = 0.;
+    for (level, count) in levels.iter().enumerate() {
+        dark_count += *count as f64;
+        dark_sum += level as f64 * *count as f64;
+        let bright_count = total - dark_count;
+        if dark_count == 0. || bright_count == 0. {
+            continue;
+        }
+        let mean_difference = dark_sum / dark_count - (total_sum - dark_sum) / bright_count;
+        let variance = dark_count * bright_count * mean_difference * mean_difference;
+        if variance > best.1 {
+            best = (level, variance);
+        }
+    }
+
+    best.0 as u8
+}
+
+/// compares every pixel to the mean luminance of the block around it instead of a single level
+/// so uneven lighting doesn't swallow parts of the image, the mean is weighted with a gaussian
+/// when gaussian is set and the offset is subtracted from it
+pub fn adaptive_threshold(
+    input_image: Vec<u8>,
+    width: u32,
+    block_size: u32,
+    offset: f32,
+    gaussian: bool,
+) -> Vec<u8> {
+    let height = (input_image.len() as u32 / CHANNEL_COUNT) / width;
+    let luminance = luminance(&input_image);
+
+    let radius = (block_size / 2) as i32;
+    // the same sigma opencv picks for a kernel of this size
+    let sigma = 0.3 * ((block_size as f32 - 1.) * 0.5 - 1.) + 0.8;
+    let weights = (-radius..=radius)
+        .map(|x| {
+            if gaussian {
+                (-((x * x) as f32) / (2. * sigma * sigma)).exp()
+            } else {
+                1.
+            }
+        })
+        .collect::<Vec<_>>();
+    let sum: f32 = weights.iter().sum();
+    let kernel = weights
+        .iter()
+        .map(|weight| weight / sum)
+        .collect::<Vec<_>>();
+
+    let local_mean = separable_convolution(&luminance, width as usize, height as usize, 1, &kernel);
+    binarize(input_image, &luminance, |index, value| {
+        value > local_mean[index] - offset
+    })
+}
+
+/// makes the pixels the predicate accepts white and the rest black, the predicate gets the index
+/// of the pixel and its luminance
+fn binarize(
+    input_image: Vec<u8>,
+    luminance: &[f32],
+    is_white: impl Fn(usize, f32) -> bool,
+) -> Vec<u8> {
+    let mut image = input_image;
+    for (index, (pixel, value)) in image
+        .chunks_exact_mut(CHANNEL_COUNT as usize)
+        .zip(luminance)
+        .enumerate()
+    {
+        let sample = if is_white(index, *value) { u8::MAX } else { 0 };
+        // alpha is left untouched
+        pixel[..3].fill(sample);
+    }
+
+    image
+}
+
+/// shows the 2d fourier transform of the image's luminance with the zero frequency in the center
+/// the magnitude is log scaled because the low frequencies are orders of magnitude larger than
+/// the rest, the phase is mapped from -pi..pi to black..white
+pub fn frequency_spectrum(input_image: Vec<u8>, width: u32, show_phase: bool) -> Vec<u8> {
+    let height = (input_image.len() as u32 / CHANNEL_COUNT) / width;
+    let (width, height) = (width as usize, height as usize);
+
+    let mut spectrum = luminance(&input_image)
+        .into_iter()
+        .map(|value| Complex::new(value, 0.))
+        .collect::<Vec<_>>();
+    fft_2d(&mut spectrum, width, height, false);
+
+    let values = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let value = spectrum[shifted_index(x, y, width, height)];
+            if show_phase {
+                value.phase()
+            } else {
+                value.magnitude().ln_1p()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let (min, max) = if show_phase {
+        (-PI, PI)
+    } else {
+        (0., values.iter().copied().fold(0., f32::max))
+    };
+    let range = (max - min).max(f32::EPSILON);
+
+    let mut image = input_image;
+    for (pixel, value) in image.chunks_exact_mut(CHANNEL_COUNT as usize).zip(values) {
+        let gray = ((value - min) / range * 255.).round().clamp(0., 255.) as u8;
+        pixel[0] = gray;
+        pixel[1] = gray;
+        pixel[2] = gray;
+        pixel[3] = u8::MAX;
+    }
+
+    image
+}
+
+/// filters every color channel by multiplying its spectrum with the filter's mask
+/// also returns the luminance spectrum of the input with the mask overlaid on it, frequencies the
+/// filter removes are tinted red
+/// this runs on full resolution images so only one channel is transformed at a time and written
+/// back into the image before the next one, the mask is computed where it's used instead of kept
+pub fn frequency_filter(
+    input_image: Vec<u8>,
+    width: u32,
+    filter: &FrequencyFilter,
+) -> (Vec<u8>, Vec<u8>) {
+    let height = (input_image.len() as u32 / CHANNEL_COUNT) / width;
+    let (width, height) = (width as usize, height as usize);
+
+    // the mask is in centered order, the same order the spectrum preview is drawn in
+    let mask = |x: usize, y: usize| {
+        let u = (x as f64 - (width / 2) as f64) / (width as f64 / 2.);
+        let v = (y as f64 - (height / 2) as f64) / (height as f64 / 2.);
+        frequency_filter_response(filter, u.hypot(v)) as f32
+    };
+
+    // the fourier transform is linear so the luminance spectrum is the weighted sum of the
+    // channel spectra and doesn't need its own transform
+    let mut image = input_image;
+    let mut luminance_spectrum = vec![Complex::default(); width * height];
+    let mut spectrum = vec![Complex::default(); width * height];
+    for (channel, weight) in LUMINANCE_WEIGHTS.into_iter().enumerate() {
+        for (value, pixel) in spectrum
+            .iter_mut()
+            .zip(image.chunks_exact(CHANNEL_COUNT as usize))
+        {
+            *value = Complex::new(pixel[channel] as f32, 0.);
+        }
+        fft_2d(&mut spectrum, width, height, false);
+
+        for (luminance, value) in luminance_spectrum.iter_mut().zip(spectrum.iter()) {
+            *luminance = *luminance + value.scale(weight);
+        }
+        for y in 0..height {
+            for x in 0..width {
+                let index = shifted_index(x, y, width, height);
+                spectrum[index] = spectrum[index].scale(mask(x, y));
+            }
+        }
+
+        fft_2d(&mut spectrum, width, height, true);
+        for (pixel, value) in image
+            .chunks_exact_mut(CHANNEL_COUNT as usize)
+            .zip(spectrum.iter())
+        {
+            pixel[channel] = value.re.round().clamp(0., 255.) as u8;
+        }
+    }
+    drop(spectrum);
+
+    let magnitude = |x: usize, y: usize| {
+        luminance_spectrum[shifted_index(x, y, width, height)]
+            .magnitude()
+            .ln_1p()
+    };
+    let max = luminance_spectrum
+        .iter()
+        .map(|value| value.magnitude().ln_1p())
+        .fold(0., f32::max)
+        .max(f32::EPSILON);
+    let preview = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            let gray = magnitude(x, y) / max * 255.;
+            let removed = 0.5 * (1. - mask(x, y).clamp(0., 1.));
+            let red = gray + (255. - gray) * removed;
+            let rest = gray * (1. - removed);
+            [
+                red.round() as u8,
+                rest.round() as u8,
+                rest.round() as u8,
+                u8::MAX,
+            ]
+        })
+        .collect();
+
+    (image, preview)
+}
+
+/// how much of a frequency the filter keeps, distance is how far the frequency is from the zero
+/// frequency as a fraction of the highest horizontal or vertical frequency
+pub fn frequency_filter_response(filter: &FrequencyFilter, distance: f64) -> f64 {
+    let cutoff = filter.cutoff;
+    let order = filter.order as i32;
+    match filter.band {
+        FrequencyBand::LowPass | FrequencyBand::HighPass => {
+            let low_pass = match filter.shape {
+                FilterShape::Ideal => (distance <= cutoff) as u8 as f64,
+                FilterShape::Butterworth => 1. / (1. + (distance / cutoff).powi(2 * order)),
+                FilterShape::Gaussian => (-distance.powi(2) / (2. * cutoff.powi(2))).exp(),
+            };
+            if filter.band == FrequencyBand::HighPass {
+                1. - low_pass
+            } else {
+                low_pass
+            }
+        }
+        FrequencyBand::BandPass => {
+            let bandwidth = filter.bandwidth;
+            if filter.shape == FilterShape::Ideal {
+                return ((distance - cutoff).abs() <= bandwidth / 2.) as u8 as f64;
+            }
+            // the band is centered on the cutoff, the zero frequency is never part of it
+            if distance == 0. {
+                return 0.;
+            }
+            let offset = (distance.powi(2) - cutoff.powi(2)) / (distance * bandwidth);
+            match filter.shape {
+                FilterShape::Butterworth => 1. / (1. + offset.powi(2 * order)),
+                _ => (-offset.powi(2)).exp(),
+            }
+        }
+    }
+}
+
+/// corners weaker than this fraction of the strongest corner are ignored
+const CORNER_QUALITY_LEVEL: f32 = 0.01;
+
+/// finds corners from the structure tensor of every pixel, the strongest corners are kept first
+/// and any corner within the min distance of a kept corner is suppressed
+pub fn detect_corners(image: &[u8], width: u32, detector: &CornerDetector) -> Vec<Corner> {
+    let height = (image.len() as u32 / CHANNEL_COUNT) / width;
+    let (width, height) = (width as usize, height as usize);
+    let gray = luminance(image)
+        .into_iter()
+        .map(|value| value / 255.)
+        .collect::<Vec<_>>();
+
+    // the gradients are stored as the interleaved products ix², iy², ixiy
+    let products = sobel_gradients(&gray, width, height)
+        .into_iter()
+        .flat_map(|(gradient_x, gradient_y)| {
+            [
+                gradient_x * gradient_x,
+                gradient_y * gradient_y,
+                gradient_x * gradient_y,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let window_size = detector.window_size as usize;
+    let window = vec![1. / window_size as f32; window_size];
+    let tensors = separable_convolution(&products, width, height, 3, &window);
+
+    let k = detector.k as f32;
+    let responses = tensors
+        .chunks_exact(3)
+        .map(|tensor| {
+            let (xx, yy, xy) = (tensor[0], tensor[1], tensor[2]);
+            let trace = xx + yy;
+            match detector.method {
+                CornerMethod::Harris => xx * yy - xy * xy - k * trace * trace,
+                CornerMethod::ShiTomasi => {
+                    trace / 2. - ((xx - yy) * (xx - yy) / 4. + xy * xy).sqrt()
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let max_response = responses.iter().copied().fold(0., f32::max);
+    if max_response <= 0. {
+        return Vec::new();
+    }
+    let threshold = max_response * CORNER_QUALITY_LEVEL;
+
+    // only local maxima of their 3x3 neighbourhood are candidates
+    let mut candidates = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let response = responses[y * width + x];
+            if response < threshold {
+                continue;
+            }
+            let is_maximum = (y.saturating_sub(1)..(y + 2).min(height)).all(|neighbour_y| {
+                (x.saturating_sub(1)..(x + 2).min(width))
+                    .all(|neighbour_x| responses[neighbour_y * width + neighbour_x] <= response)
+            });
+            if is_maximum {
+                candidates.push(Corner {
+                    x: x as u32,
+                    y: y as u32,
+                    response,
+                });
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.response.total_cmp(&a.response));
+
+    // kept corners are bucketed in a grid of min distance sized cells so each candidate only
+    // checks the cells around it
+    let min_distance = detector.min_distance.max(1) as usize;
+    let grid_width = width.div_ceil(min_distance);
+    let grid_height = height.div_ceil(min_distance);
+    let mut grid: Vec<Vec<Corner>> = vec![Vec::new(); grid_width * grid_height];
+    let mut corners = Vec::new();
+    for candidate in candidates {
+        if corners.len() >= detector.max_corners as usize {
+            break;
+        }
+        let cell_x = candidate.x as usize / min_distance;
+        let cell_y = candidate.y as usize / min_distance;
+        let is_isolated = (cell_y.saturating_sub(1)..(cell_y + 2).min(grid_height)).all(|y| {
+            (cell_x.saturating_sub(1)..(cell_x + 2).min(grid_width)).all(|x| {
+                grid[y * grid_width + x].iter().all(|corner| {
+                    let dx = corner.x as f32 - candidate.x as f32;
+                    let dy = corner.y as f32 - candidate.y as f32;
+                    dx * dx + dy * dy >= (min_distance * min_distance) as f32
+                })
+            })
+        });
+        if is_isolated {
+            grid[cell_y * grid_width + cell_x].push(candidate);
+            corners.push(candidate);
+        }
+    }
+
+    corners
+}
+
+/// selects the pixels whose color is within the tolerance of the seed pixel, the difference is
+/// the largest difference of the red, green and blue channels
+/// contiguous selections only grow from the seed through selected neighbours like a flood fill,
+/// otherwise every matching pixel in the image is selected
+pub fn select_color_range(
+    image: &[u8],
+    width: u32,
+    seed_x: u32,
+    seed_y: u32,
+    tolerance: u8,
+    contiguous: bool,
+) -> Vec<f32> {
+    let height = (image.len() as u32 / CHANNEL_COUNT) / width;
+    let (width, height) = (width as usize, height as usize);
+    let mut mask = vec![0.; width * height];
+    if seed_x as usize >= width || seed_y as usize >= height {
+        return mask;
+    }
+
+    let seed_index = seed_y as usize * width + seed_x as usize;
+    let seed = &image[seed_index * CHANNEL_COUNT as usize..][..3];
+    let matches = |index: usize| {
+        image[index * CHANNEL_COUNT as usize..][..3]
+            .iter()
+            .zip(seed)
+            .all(|(value, seed)| value.abs_diff(*seed) <= tolerance)
+    };
+
+    if !contiguous {
+        for (index, selected) in mask.iter_mut().enumerate() {
+            if matches(index) {
+                *selected = 1.;
+            }
+        }
+        return mask;
+    }
+
+    let mut stack = vec![seed_index];
+    mask[seed_index] = 1.;
+    while let Some(index) = stack.pop() {
+        let (x, y) = (index % width, index / width);
+        let neighbours = [
+            (x > 0).then(|| index - 1),
+            (x + 1 < width).then(|| index + 1),
+            (y > 0).then(|| index - width),
+            (y + 1 < height).then(|| index + width),
+        ];
+        for neighbour in neighbours.into_iter().flatten() {
+            if mask[neighbour] == 0. && matches(neighbour) {
+                mask[neighbour] = 1.;
+                stack.push(neighbour);
+            }
+        }
+    }
+
+    mask
+}
+
+/// softens the edge of a mask with a gaussian blur, a radius of 0 keeps the hard edge
+pub fn feather_mask(mask: Vec<f32>, width: u32, radius: f32) -> Vec<f32> {
+    if radius <= 0. {
+        return mask;
+    }
+    let height = mask.len() / width as usize;
+    separable_convolution(&mask, width as usize, height, 1, &gaussian_kernel(radius))
+}
+
+/// mixes the processed image into the original by the mask, the alpha channel is mixed too
+/// since some steps change it
+pub fn blend_with_mask(original: &[u8], processed: Vec<u8>, mask: &[f32]) -> Vec<u8> {
+    let mut image = processed;
+    for ((pixel, original), weight) in image
+        .chunks_exact_mut(CHANNEL_COUNT as usize)
+        .zip(original.chunks_exact(CHANNEL_COUNT as usize))
+        .zip(mask)
+    {
+        for (value, original) in pixel.iter_mut().zip(original) {
+            *value = (*original as f32 + (*value as f32 - *original as f32) * weight).round() as u8;
+        }
+    }
+    image
+}
+
+/// an image that is transparent except for the border of the mask which is drawn as black and
+/// white dashes so it shows up on top of any image
+pub fn mask_outline(mask: &[f32], width: u32) -> Vec<u8> {
+    let width = width as usize;
+    let height = mask.len() / width;
+    let is_selected = |x: usize, y: usize| mask[y * width + x] >= 0.5;
+
+    let mut outline = vec![0; mask.len() * CHANNEL_COUNT as usize];
+    for y in 0..height {
+        for x in 0..width {
+            if !is_selected(x, y) {
+                continue;
+            }
+            let is_border = x == 0
+                || y == 0
+                || x + 1 == width
+                || y + 1 == height
+                || !is_selected(x - 1, y)
+                || !is_selected(x + 1, y)
+                || !is_selected(x, y - 1)
+                || !is_selected(x, y + 1);
+            if is_border {
+                let value = if (x + y) / 4 % 2 == 0 { u8::MAX } else { 0 };
+                let pixel = &mut outline[(y * width + x) * CHANNEL_COUNT as usize..][..4];
+                pixel.copy_from_slice(&[value, value, value, u8::MAX]);
+            }
+        }
+    }
+    outline
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a gray image with the value of every pixel, the alpha is 255
+    fn gray_image(width: usize, height: usize, value: impl Fn(usize, usize) -> u8) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|index| {
+                let gray = value(index % width, index / width);
+                [gray, gray, gray, 255]
+            })
+            .collect()
+    }
+
+    fn grays(image: &[u8]) -> Vec<u8> {
+        image.chunks_exact(4).map(|pixel| pixel[0]).collect()
+    }
+
+    #[test]
+    fn color_range_selection_is_contiguous_when_asked() {
+        // two white columns split by a black one
+        let image = gray_image(5, 3, |x, _| if x == 2 { 0 } else { 255 });
+        let contiguous = select_color_range(&image, 5, 0, 0, 10, true);
+        assert_eq!(contiguous.iter().filter(|&&value| value == 1.).count(), 6);
+        let everywhere = select_color_range(&image, 5, 0, 0, 10, false);
+        assert_eq!(everywhere.iter().filter(|&&value| value == 1.).count(), 12);
+    }
+
+    #[test]
+    fn color_range_selection_outside_the_image_is_empty() {
+        let image = gray_image(5, 3, |_, _| 255);
+        assert!(select_color_range(&image, 5, 5, 0, 10, true)
+            .iter()
+            .all(|&value| value == 0.));
+    }
+
+    #[test]
+    fn equalization_uses_the_whole_range() {
+        let image = gray_image(30, 20, |x, _| 100 + (x / 2) as u8);
+        let luminance = luminance(&equalize_histogram(image));
+        let min = luminance.iter().copied().fold(f32::MAX, f32::min);
+        let max = luminance.iter().copied().fold(f32::MIN, f32::max);
+        assert!(min < 1. && max > 254., "{} to {}", min, max);
+    }
+
+    #[test]
+    fn clahe_leaves_a_flat_image_almost_alone() {
+        let image = gray_image(37, 23, |_, _| 77);
+        for tile_grid_size in [1, 8, 100] {
+            let equalized = clahe(image.clone(), 37, tile_grid_size, 2.);
+            assert_eq!(equalized.len(), image.len());
+            assert!(grays(&equalized)
+                .iter()
+                .all(|value| value.abs_diff(77) <= 2));
+        }
+    }
+
+    #[test]
+    fn otsu_splits_a_bimodal_image() {
+        let image = gray_image(20, 10, |x, y| {
+            if x < 10 {
+                40 + (y % 3) as u8
+            } else {
+                200 - (y % 3) as u8
+            }
+        });
+        let level = otsu_level(&image);
+        assert!((42..198).contains(&level), "level {}", level);
+
+        let binary = grays(&threshold(image, level));
+        assert!(binary
+            .chunks(20)
+            .all(|row| row[..10].iter().all(|&value| value == 0)
+                && row[10..].iter().all(|&value| value == 255)));
+    }
+
+    #[test]
+    fn adaptive_threshold_finds_spots_on_a_gradient() {
+        let image = gray_image(50, 30, |x, y| {
+            let background = (x * 4) as u8;
+            if x % 10 == 5 && y % 10 == 5 {
+                background + 30
+            } else {
+                background
+            }
+        });
+        for gaussian in [false, true] {
+            let binary = grays(&adaptive_threshold(image.clone(), 50, 7, -5., gaussian));
+            let white = binary.iter().filter(|&&value| value == 255).count();
+            assert_eq!(white, 15);
+        }
+    }
+
+    #[test]
+    fn canny_only_marks_the_outline_of_a_square() {
+        let (width, height) = (40, 30);
+        let image = gray_image(width, height, |x, y| {
+            if (10..30).contains(&x) && (8..22).contains(&y) {
+                220
+            } else {
+                30
+            }
+        });
+        let edges = grays(&canny_edge_detection(image, width as u32, 1.4, 20., 50.));
+        let is_edge = |x: usize, y: usize| edges[y * width + x] == 255;
+
+        // every edge is within 2 pixels of the border of the square
+        let near_border = |x: usize, y: usize| {
+            let inside_outer = (8..=31).contains(&x) && (6..=23).contains(&y);
+            let inside_inner = (13..=26).contains(&x) && (11..=18).contains(&y);
+            inside_outer && !inside_inner
+        };
+        for y in 0..height {
+            for x in 0..width {
+                assert!(!is_edge(x, y) || near_border(x, y), "edge at {}, {}", x, y);
+            }
+        }
+        // and every side of the square has one
+        for y in 10..20 {
+            assert!((8..=12).any(|x| is_edge(x, y)) && (27..=31).any(|x| is_edge(x, y)));
+        }
+        for x in 12..28 {
+            assert!((6..=10).any(|y| is_edge(x, y)) && (19..=23).any(|y| is_edge(x, y)));
+        }
+    }
+}
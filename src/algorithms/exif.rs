@@ -0,0 +1,396 @@
+//! reads the metadata stored in jpeg files, only the few exif tags shown in the app are parsed
+
+const SOI: [u8; 2] = [0xFF, 0xD8];
+const APP1: u8 = 0xE1;
+const APP2: u8 = 0xE2;
+const SOS: u8 = 0xDA;
+const EOI: u8 = 0xD9;
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const ICC_HEADER: &[u8] = b"ICC_PROFILE\0";
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_EXPOSURE_TIME: u16 = 0x829A;
+const TAG_F_NUMBER: u16 = 0x829D;
+const TAG_ISO: u16 = 0x8827;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_COLOR_SPACE: u16 = 0xA001;
+
+const TYPE_ASCII: u16 = 2;
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+const TYPE_RATIONAL: u16 = 5;
+
+/// the metadata found in a jpeg, every field is none when the file doesn't have it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JpegMetadata {
+    /// the exif orientation, 1 is upright and 2 to 8 are the mirrored and rotated versions
+    pub orientation: Option<u16>,
+    pub camera: Option<String>,
+    pub exposure: Option<String>,
+    pub iso: Option<u32>,
+    pub date: Option<String>,
+    pub color_profile: Option<String>,
+}
+
+/// files that aren't jpegs or have broken metadata return whatever could be read before the
+/// problem, decoding the image never depends on this succeeding
+pub fn read_jpeg_metadata(file: &[u8]) -> JpegMetadata {
+    let mut metadata = JpegMetadata::default();
+    let mut icc_chunks = Vec::new();
+    let mut color_space = None;
+
+    for (marker, payload) in jpeg_segments(file) {
+        if marker == APP1 && payload.starts_with(EXIF_HEADER) {
+            if let Some(tiff) = Tiff::new(&payload[EXIF_HEADER.len()..]) {
+                color_space = tiff.read_metadata(&mut metadata);
+            }
+        } else if marker == APP2 && payload.starts_with(ICC_HEADER) {
+            // large profiles are split across several segments that each know their position
+            if let [sequence_number, _, chunk @ ..] = &payload[ICC_HEADER.len()..] {
+                icc_chunks.push((*sequence_number, chunk));
+            }
+        }
+    }
+
+    icc_chunks.sort_by_key(|(sequence_number, _)| *sequence_number);
+    let icc_profile = icc_chunks
+        .into_iter()
+        .flat_map(|(_, chunk)| chunk.iter().copied())
+        .collect::<Vec<_>>();
+    metadata.color_profile = icc_description(&icc_profile).or(match color_space {
+        Some(1) => Some("sRGB".to_string()),
+        Some(0xFFFF) => Some("Uncalibrated".to_string()),
+        _ => None,
+    });
+
+    metadata
+}
+
+/// the marker and payload of every segment before the image data starts
+fn jpeg_segments(file: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut segments = Vec::new();
+    if !file.starts_with(&SOI) {
+        return segments;
+    }
+
+    let mut position = SOI.len();
+    while position + 4 <= file.len() && file[position] == 0xFF {
+        let marker = file[position + 1];
+        // markers can be padded with any number of fill bytes
+        if marker == 0xFF {
+            position += 1;
+            continue;
+        }
+        if marker == SOS || marker == EOI {
+            break;
+        }
+        // the length counts its own two bytes but not the marker
+        let length = u16::from_be_bytes([file[position + 2], file[position + 3]]) as usize;
+        let end = position + 2 + length;
+        if length < 2 || end > file.len() {
+            break;
+        }
+        segments.push((marker, &file[position + 4..end]));
+        position = end;
+    }
+
+    segments
+}
+
+/// the tiff structure exif is stored in, offsets are relative to the start of its header
+struct Tiff<'a> {
+    data: &'a [u8],
+    is_little_endian: bool,
+}
+
+struct Entry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    /// where the value is stored, values over 4 bytes only store an offset here
+    value_position: usize,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Tiff<'a>> {
+        let is_little_endian = match data.get(..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        let tiff = Tiff {
+            data,
+            is_little_endian,
+        };
+        (tiff.u16(2)? == 42).then_some(tiff)
+    }
+
+    /// fills in the metadata from the first image directory and the exif directory it points
+    /// to, the color space tag is returned since an icc profile takes priority over it
+    fn read_metadata(&self, metadata: &mut JpegMetadata) -> Option<u16> {
+        let image_entries = self.entries(self.u32(4)? as usize);
+        let exif_entries = image_entries
+            .iter()
+            .find(|entry| entry.tag == TAG_EXIF_IFD)
+            .and_then(|entry| self.unsigned(entry))
+            .map(|offset| self.entries(offset as usize))
+            .unwrap_or_default();
+        let find = |tag: u16| {
+            image_entries
+                .iter()
+                .chain(exif_entries.iter())
+                .find(|entry| entry.tag == tag)
+        };
+
+        metadata.orientation = find(TAG_ORIENTATION)
+            .and_then(|entry| self.unsigned(entry))
+            .and_then(|orientation| u16::try_from(orientation).ok())
+            .filter(|orientation| (1..=8).contains(orientation));
+
+        let make = find(TAG_MAKE).and_then(|entry| self.ascii(entry));
+        let model = find(TAG_MODEL).and_then(|entry| self.ascii(entry));
+        metadata.camera = match (make, model) {
+            // most cameras repeat the make at the start of the model
+            (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            (make, model) => make.or(model),
+        };
+
+        let exposure_time = find(TAG_EXPOSURE_TIME)
+            .and_then(|entry| self.rational(entry))
+            .map(format_exposure_time);
+        let f_number = find(TAG_F_NUMBER)
+            .and_then(|entry| self.rational(entry))
+            .map(|(numerator, denominator)| format!("f/{}", numerator as f64 / denominator as f64));
+        metadata.exposure = match (exposure_time, f_number) {
+            (Some(exposure_time), Some(f_number)) => {
+                Some(format!("{} at {}", exposure_time, f_number))
+            }
+            (exposure_time, f_number) => exposure_time.or(f_number),
+        };
+
+        metadata.iso = find(TAG_ISO).and_then(|entry| self.unsigned(entry));
+
+        // exif dates look like 2024:01:31 12:00:00
+        metadata.date = find(TAG_DATE_TIME_ORIGINAL)
+            .or_else(|| find(TAG_DATE_TIME))
+            .and_then(|entry| self.ascii(entry))
+            .map(|date| date.replacen(':', "-", 2));
+
+        find(TAG_COLOR_SPACE)
+            .and_then(|entry| self.unsigned(entry))
+            .and_then(|color_space| u16::try_from(color_space).ok())
+    }
+
+    /// the entries of the directory at the offset, a broken directory has no entries
+    fn entries(&self, offset: usize) -> Vec<Entry> {
+        let Some(count) = self.u16(offset) else {
+            return Vec::new();
+        };
+        // the offset is known to be inside the data so the entry positions can't overflow
+        (0..count as usize)
+            .map_while(|index| {
+                let position = offset + 2 + index * 12;
+                Some(Entry {
+                    tag: self.u16(position)?,
+                    field_type: self.u16(position + 2)?,
+                    count: self.u32(position + 4)?,
+                    value_position: position + 8,
+                })
+            })
+            .collect()
+    }
+
+    /// the bytes of the entry's value
+    fn value(&self, entry: &Entry, type_size: usize) -> Option<&'a [u8]> {
+        let start = self.value_start(entry, type_size)?;
+        self.data
+            .get(start..start + type_size * entry.count as usize)
+    }
+
+    /// where the entry's value starts, none when the value doesn't fit in the data
+    fn value_start(&self, entry: &Entry, type_size: usize) -> Option<usize> {
+        let size = type_size.checked_mul(entry.count as usize)?;
+        let start = if size <= 4 {
+            entry.value_position
+        } else {
+            self.u32(entry.value_position)? as usize
+        };
+        (entry.count > 0 && start.checked_add(size)? <= self.data.len()).then_some(start)
+    }
+
+    fn ascii(&self, entry: &Entry) -> Option<String> {
+        if entry.field_type != TYPE_ASCII {
+            return None;
+        }
+        let bytes = self.value(entry, 1)?;
+        let text = bytes.split(|&byte| byte == 0).next()?;
+        let text = String::from_utf8_lossy(text).trim().to_string();
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// the first value of a short or long entry
+    fn unsigned(&self, entry: &Entry) -> Option<u32> {
+        match entry.field_type {
+            TYPE_SHORT => self.u16(self.value_start(entry, 2)?).map(u32::from),
+            TYPE_LONG => self.u32(self.value_start(entry, 4)?),
+            _ => None,
+        }
+    }
+
+    /// the numerator and denominator of the first value of a rational entry
+    fn rational(&self, entry: &Entry) -> Option<(u32, u32)> {
+        if entry.field_type != TYPE_RATIONAL {
+            return None;
+        }
+        let position = self.value_start(entry, 8)?;
+        let denominator = self.u32(position + 4)?;
+        (denominator != 0).then_some((self.u32(position)?, denominator))
+    }
+
+    fn u16(&self, position: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self
+            .data
+            .get(position..position.checked_add(2)?)?
+            .try_into()
+            .ok()?;
+        Some(if self.is_little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, position: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self
+            .data
+            .get(position..position.checked_add(4)?)?
+            .try_into()
+            .ok()?;
+        Some(if self.is_little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+}
+
+/// short exposures are written as fractions of a second the way cameras show them
+fn format_exposure_time((numerator, denominator): (u32, u32)) -> String {
+    let seconds = numerator as f64 / denominator as f64;
+    if seconds < 1. && numerator != 0 {
+        format!("1/{} s", (1. / seconds).round())
+    } else {
+        format!("{} s", (seconds * 10.).round() / 10.)
+    }
+}
+
+/// the name of an icc profile from its description tag, which is plain ascii in version 2
+/// profiles and utf-16 in version 4 profiles
+fn icc_description(profile: &[u8]) -> Option<String> {
+    // the tag table follows the 128 byte header
+    let tag_count = be_u32(profile, 128)?;
+    let tag = (0..tag_count)
+        .map_while(|index| {
+            let position = 132 + index * 12;
+            Some((profile.get(position..position + 4)?, position))
+        })
+        .find(|(signature, _)| *signature == b"desc")
+        .and_then(|(_, position)| {
+            let offset = be_u32(profile, position + 4)?;
+            profile.get(offset..offset.checked_add(be_u32(profile, position + 8)?)?)
+        })?;
+
+    let description = match tag.get(..4)? {
+        b"desc" => {
+            let length = be_u32(tag, 8)?;
+            let text = tag.get(12..length.checked_add(12)?)?;
+            let text = text.split(|&byte| byte == 0).next()?;
+            String::from_utf8_lossy(text).to_string()
+        }
+        b"mluc" => {
+            // the first record is used, each record has a language, a length and an offset
+            let length = be_u32(tag, 20)?;
+            let offset = be_u32(tag, 24)?;
+            let text = tag.get(offset..offset.checked_add(length)?)?;
+            let units = text
+                .chunks_exact(2)
+                .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                .collect::<Vec<_>>();
+            String::from_utf16_lossy(&units)
+        }
+        _ => return None,
+    };
+    let description = description.trim().to_string();
+    (!description.is_empty()).then_some(description)
+}
+
+fn be_u32(data: &[u8], position: usize) -> Option<usize> {
+    let bytes: [u8; 4] = data.get(position..position + 4)?.try_into().ok()?;
+    Some(u32::from_be_bytes(bytes) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a jpeg with a little endian exif segment holding the orientation and the camera make
+    fn jpeg_with_exif(orientation: u16) -> Vec<u8> {
+        let mut tiff = b"II".to_vec();
+        tiff.extend(42u16.to_le_bytes());
+        tiff.extend(8u32.to_le_bytes());
+        // the directory has 2 entries followed by the offset of the next directory, the make is
+        // too long to fit in its entry so it's stored after that
+        let make_offset = 8 + 2 + 2 * 12 + 4;
+        tiff.extend(2u16.to_le_bytes());
+        for (tag, field_type, count, value) in [
+            (TAG_ORIENTATION, TYPE_SHORT, 1u32, orientation as u32),
+            (TAG_MAKE, TYPE_ASCII, 6, make_offset),
+        ] {
+            tiff.extend(tag.to_le_bytes());
+            tiff.extend(field_type.to_le_bytes());
+            tiff.extend(count.to_le_bytes());
+            tiff.extend(value.to_le_bytes());
+        }
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(b"Canon\0");
+
+        let payload = [EXIF_HEADER, &tiff].concat();
+        let mut file = SOI.to_vec();
+        file.extend([0xFF, APP1]);
+        file.extend((payload.len() as u16 + 2).to_be_bytes());
+        file.extend(payload);
+        file.extend([0xFF, SOS, 0, 2, 0xFF, EOI]);
+        file
+    }
+
+    #[test]
+    fn reads_the_orientation_and_camera() {
+        let metadata = read_jpeg_metadata(&jpeg_with_exif(6));
+        assert_eq!(metadata.orientation, Some(6));
+        assert_eq!(metadata.camera.as_deref(), Some("Canon"));
+    }
+
+    #[test]
+    fn ignores_orientations_out_of_range() {
+        assert_eq!(read_jpeg_metadata(&jpeg_with_exif(9)).orientation, None);
+    }
+
+    #[test]
+    fn truncated_files_do_not_panic() {
+        let file = jpeg_with_exif(3);
+        for len in 0..file.len() {
+            read_jpeg_metadata(&file[..len]);
+        }
+    }
+
+    #[test]
+    fn other_files_have_no_metadata() {
+        assert_eq!(read_jpeg_metadata(b"\x89PNG\r\n"), JpegMetadata::default());
+    }
+}
@@ -0,0 +1,314 @@
+use std::f64::consts::PI;
+use std::ops::{Add, Mul, Sub};
+
+/// a complex number in single precision, a spectrum of a full resolution image has one per pixel
+/// so halving their size matters more than the precision an 8 bit image can't show
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub fn new(re: f32, im: f32) -> Complex {
+        Complex { re, im }
+    }
+
+    /// the complex number with a magnitude of 1 at the angle in radians
+    /// the angle is in double precision so twiddles and chirps of long rows stay accurate
+    pub fn from_angle(angle: f64) -> Complex {
+        Complex::new(angle.cos() as f32, angle.sin() as f32)
+    }
+
+    pub fn conj(self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+
+    pub fn magnitude(self) -> f32 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn phase(self) -> f32 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn scale(self, factor: f32) -> Complex {
+        Complex::new(self.re * factor, self.im * factor)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+/// a precomputed fourier transform for one length
+/// power of two lengths use an iterative radix 2 fft, every other length uses bluestein's
+/// algorithm which turns the transform into a convolution that can be done with a power of two
+/// fft, this way images don't have to be padded or cropped
+pub struct Fft {
+    len: usize,
+    algorithm: FftAlgorithm,
+}
+
+enum FftAlgorithm {
+    Radix2 {
+        twiddles: Vec<Complex>,
+    },
+    Bluestein {
+        inner: Box<Fft>,
+        chirp: Vec<Complex>,
+        /// the transformed conjugate chirp that the input gets convolved with
+        chirp_filter: Vec<Complex>,
+    },
+}
+
+impl Fft {
+    pub fn new(len: usize) -> Fft {
+        if len.is_power_of_two() || len <= 1 {
+            let twiddles = (0..len / 2)
+                .map(|k| Complex::from_angle(-2. * PI * k as f64 / len as f64))
+                .collect();
+            return Fft {
+                len,
+                algorithm: FftAlgorithm::Radix2 { twiddles },
+            };
+        }
+
+        let inner_len = (2 * len - 1).next_power_of_two();
+        let inner = Fft::new(inner_len);
+        // k² is reduced modulo 2n so the angle stays accurate for long rows, it is computed in
+        // 64 bits since it overflows a 32 bit usize for rows longer than 65535
+        let chirp = (0..len)
+            .map(|k| {
+                let k_squared = (k as u64 * k as u64) % (2 * len as u64);
+                Complex::from_angle(-PI * k_squared as f64 / len as f64)
+            })
+            .collect::<Vec<_>>();
+
+        let mut chirp_filter = vec![Complex::default(); inner_len];
+        chirp_filter[0] = chirp[0].conj();
+        for k in 1..len {
+            chirp_filter[k] = chirp[k].conj();
+            chirp_filter[inner_len - k] = chirp[k].conj();
+        }
+        inner.forward(&mut chirp_filter);
+
+        Fft {
+            len,
+            algorithm: FftAlgorithm::Bluestein {
+                inner: Box::new(inner),
+                chirp,
+                chirp_filter,
+            },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn forward(&self, data: &mut [Complex]) {
+        debug_assert_eq!(data.len(), self.len);
+        match &self.algorithm {
+            FftAlgorithm::Radix2 { twiddles } => radix2(data, twiddles),
+            FftAlgorithm::Bluestein {
+                inner,
+                chirp,
+                chirp_filter,
+            } => {
+                let mut convolution = vec![Complex::default(); inner.len()];
+                for (k, value) in data.iter().enumerate() {
+                    convolution[k] = *value * chirp[k];
+                }
+                inner.forward(&mut convolution);
+                for (value, filter) in convolution.iter_mut().zip(chirp_filter) {
+                    *value = *value * *filter;
+                }
+                inner.inverse(&mut convolution);
+                for (k, value) in data.iter_mut().enumerate() {
+                    *value = convolution[k] * chirp[k];
+                }
+            }
+        }
+    }
+
+    /// the inverse transform including the 1/n scaling
+    pub fn inverse(&self, data: &mut [Complex]) {
+        data.iter_mut().for_each(|value| *value = value.conj());
+        self.forward(data);
+        let scale = 1. / self.len as f32;
+        data.iter_mut()
+            .for_each(|value| *value = value.conj().scale(scale));
+    }
+}
+
+fn radix2(data: &mut [Complex], twiddles: &[Complex]) {
+    let len = data.len();
+    if len <= 1 {
+        return;
+    }
+
+    let bits = len.trailing_zeros();
+    for i in 0..len {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    let mut size = 2;
+    while size <= len {
+        let half = size / 2;
+        let twiddle_step = len / size;
+        for start in (0..len).step_by(size) {
+            for k in 0..half {
+                let twiddle = twiddles[k * twiddle_step];
+                let even = data[start + k];
+                let odd = data[start + k + half] * twiddle;
+                data[start + k] = even + odd;
+                data[start + k + half] = even - odd;
+            }
+        }
+        size *= 2;
+    }
+}
+
+/// transforms the rows and then the columns of a row major grid of values in place
+pub fn fft_2d(data: &mut [Complex], width: usize, height: usize, inverse: bool) {
+    let row_fft = Fft::new(width);
+    for row in data.chunks_exact_mut(width) {
+        if inverse {
+            row_fft.inverse(row);
+        } else {
+            row_fft.forward(row);
+        }
+    }
+
+    let column_fft = Fft::new(height);
+    let mut column = vec![Complex::default(); height];
+    for x in 0..width {
+        for (y, value) in column.iter_mut().enumerate() {
+            *value = data[y * width + x];
+        }
+        if inverse {
+            column_fft.inverse(&mut column);
+        } else {
+            column_fft.forward(&mut column);
+        }
+        for (y, value) in column.iter().enumerate() {
+            data[y * width + x] = *value;
+        }
+    }
+}
+
+/// the index in an unshifted spectrum for a position in the centered spectrum where the zero
+/// frequency is in the middle of the image
+pub fn shifted_index(x: usize, y: usize, width: usize, height: usize) -> usize {
+    let source_x = (x + width - width / 2) % width;
+    let source_y = (y + height - height / 2) % height;
+    source_y * width + source_x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(len: usize) -> Vec<Complex> {
+        (0..len)
+            .map(|k| Complex::new((k as f32 * 0.7).sin() * 10., (k % 3) as f32))
+            .collect()
+    }
+
+    fn assert_close(actual: &[Complex], expected: &[Complex]) {
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (*actual - *expected).magnitude() < 1e-3,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn forward_matches_the_naive_transform() {
+        // a power of two uses radix 2 and the others bluestein
+        for len in [1, 2, 5, 8, 12, 31] {
+            let input = signal(len);
+            let expected = (0..len)
+                .map(|frequency| {
+                    input
+                        .iter()
+                        .enumerate()
+                        .fold(Complex::default(), |sum, (k, value)| {
+                            let angle = -2. * PI * (frequency * k) as f64 / len as f64;
+                            sum + *value * Complex::from_angle(angle)
+                        })
+                })
+                .collect::<Vec<_>>();
+
+            let mut output = input.clone();
+            Fft::new(len).forward(&mut output);
+            assert_close(&output, &expected);
+        }
+    }
+
+    #[test]
+    fn inverse_undoes_forward() {
+        for len in [4, 7, 100] {
+            let input = signal(len);
+            let fft = Fft::new(len);
+            let mut output = input.clone();
+            fft.forward(&mut output);
+            fft.inverse(&mut output);
+            assert_close(&output, &input);
+        }
+    }
+
+    #[test]
+    fn fft_2d_round_trips() {
+        let (width, height) = (6, 5);
+        let input = signal(width * height);
+        let mut output = input.clone();
+        fft_2d(&mut output, width, height, false);
+        fft_2d(&mut output, width, height, true);
+        assert_close(&output, &input);
+    }
+
+    #[test]
+    fn long_rows_keep_an_accurate_chirp() {
+        // k² overflows 32 bits for rows this long
+        let len = 70_001;
+        let mut impulse = vec![Complex::default(); len];
+        impulse[0] = Complex::new(1., 0.);
+        Fft::new(len).forward(&mut impulse);
+        assert_close(&impulse, &vec![Complex::new(1., 0.); len]);
+    }
+}
@@ -1,275 +1,805 @@
 use std::fmt;
-use std::{fmt::Display, str::FromStr};
+use std::fmt::Display;
 
-use js_sys::{Object, Reflect, Uint8ClampedArray};
-use wasm_bindgen::{Clamped, JsValue};
+use js_sys::{Array, ArrayBuffer, Uint8ClampedArray};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
 pub mod algorithms;
+pub mod exif;
+pub mod fft;
+
+/// bumped whenever a command or response changes shape so a worker cached from an older version
+/// of the site gets reported instead of failing in confusing ways, that includes adding a variant
+/// to any enum that is sent like a new operation or error
+pub const PROTOCOL_VERSION: u32 = 19;
+
+/// requests sent from the app to the worker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Command {
-    NewImage,
-    Invert,
-    BoxBlur,
-    Gamma,
-    SobelEdgeDetector,
+    /// the bytes of the image file are sent in the transferred buffer and decoded in the worker,
+    /// the file name is used to tell the format apart when the bytes don't
+    NewImage { file_name: String },
+    /// every step only changes the selected pixels when there is a selection
+    /// the request id goes up with every pipeline command and is sent back with its responses so
+    /// the app can tell which parameters an image was rendered with
+    Pipeline {
+        request_id: u32,
+        steps: Vec<Operation>,
+        selection: Option<Selection>,
+    },
+    /// applies the steps to the full resolution image and encodes it for downloading
+    Export {
+        steps: Vec<Operation>,
+        selection: Option<Selection>,
+        format: ExportFormat,
+        file_name: String,
+    },
+}
+
+/// responses sent from the worker to the app
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WorkerResponseMessage {
+    Initialized,
+    /// the decoded original image after it was turned upright, the pixels are sent in the
+    /// transferred buffer
+    Loaded {
+        metadata: ImageMetadata,
+    },
+    /// the processed image, the pixels are sent in the transferred buffer
+    Image {
+        request_id: u32,
+        width: u32,
+        height: u32,
+    },
+    /// the spectrum of the image going into the last frequency filter with the filter's mask
+    /// overlaid on it, the pixels are sent in the transferred buffer
+    FilterSpectrum {
+        request_id: u32,
+        width: u32,
+        height: u32,
+    },
+    /// the border of the selection as an image that is drawn on top of the processed image, the
+    /// pixels are sent in the transferred buffer
+    SelectionOutline {
+        request_id: u32,
+        width: u32,
+        height: u32,
+    },
+    /// the histograms of the image the steps were applied to and of the processed image
+    Histograms {
+        request_id: u32,
+        original: Histogram,
+        processed: Histogram,
+    },
+    /// the corners found by the last corner detection step in image coordinates
+    Corners {
+        request_id: u32,
+        corners: Vec<Corner>,
+    },
+    /// the level otsu's method picked for every otsu threshold step by the step's index in the
+    /// pipeline's steps
+    OtsuLevels {
+        request_id: u32,
+        levels: Vec<(u32, u8)>,
+    },
+    /// the encoded image file, the bytes are sent in the transferred buffer
+    Exported {
+        file_name: String,
+        format: ExportFormat,
+    },
+    Error(WorkerError),
+}
+
+/// how many pixels have each of the 256 values in every color channel and in the luminance
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    pub red: Vec<u32>,
+    pub green: Vec<u32>,
+    pub blue: Vec<u32>,
+    pub luminance: Vec<u32>,
+}
+
+/// what is known about the loaded image, everything but the size only comes from jpeg metadata
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    /// the size after the exif orientation was applied
+    pub width: u32,
+    pub height: u32,
+    pub camera: Option<String>,
+    /// the exposure time and aperture, like 1/125 s at f/2.8
+    pub exposure: Option<String>,
+    pub iso: Option<u32>,
+    pub date: Option<String>,
+    pub color_profile: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Png,
+    /// quality goes from 1 to 100
+    Jpeg {
+        quality: u8,
+    },
 }
 
-impl FromStr for Command {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            NEW_IMAGE => Ok(Self::NewImage),
-            INVERT => Ok(Self::Invert),
-            BOX_BLUR => Ok(Self::BoxBlur),
-            GAMMA => Ok(Self::Gamma),
-            SOBEL_EDGE_DETECTOR => Ok(Self::SobelEdgeDetector),
-            _ => Err(format!("Unsupported/Unknown command: {}", s)),
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Jpeg { .. } => "jpg",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "image/png",
+            ExportFormat::Jpeg { .. } => "image/jpeg",
         }
     }
 }
 
-impl Display for Command {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let str = match self {
-            Command::NewImage => NEW_IMAGE,
-            Command::Invert => INVERT,
-            Command::BoxBlur => BOX_BLUR,
-            Command::Gamma => GAMMA,
-            Command::SobelEdgeDetector => SOBEL_EDGE_DETECTOR,
-        };
+/// everything that can go wrong in the worker, these are sent back to the app instead of
+/// panicking so neither side goes down because of a bad message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WorkerError {
+    BadParameters(String),
+    NoImageLoaded,
+    ImageTooLarge {
+        width: u32,
+        height: u32,
+        max_pixels: u32,
+    },
+    UnsupportedFormat(String),
+    DecodeFailure(String),
+    EncodeFailure(String),
+    VersionMismatch {
+        received: Option<u32>,
+    },
+}
 
-        write!(f, "{}", str)
+impl Display for WorkerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkerError::BadParameters(error) => write!(f, "Invalid parameters: {}", error),
+            WorkerError::NoImageLoaded => write!(f, "Select an image before applying algorithms"),
+            WorkerError::ImageTooLarge {
+                width,
+                height,
+                max_pixels,
+            } => write!(
+                f,
+                "The image is too large to process ({}x{}), images can have at most {} pixels",
+                width, height, max_pixels
+            ),
+            WorkerError::UnsupportedFormat(file_name) => write!(
+                f,
+                "{} is not a supported image, use a png, jpeg, webp, bmp, tiff, gif or tga file",
+                file_name
+            ),
+            WorkerError::DecodeFailure(error) => write!(
+                f,
+                "Could not decode the image, the file may be corrupt: {}",
+                error
+            ),
+            WorkerError::EncodeFailure(error) => write!(f, "Could not export the image: {}", error),
+            WorkerError::VersionMismatch { received } => {
+                write!(
+                    f,
+                    "{}",
+                    ProtocolError::VersionMismatch {
+                        received: *received
+                    }
+                )
+            }
+        }
     }
 }
 
-const NEW_IMAGE: &str = "new image";
-const INVERT: &str = "invert";
-const BOX_BLUR: &str = "box blur";
-const GAMMA: &str = "gamma";
-const SOBEL_EDGE_DETECTOR: &str = "sobel edge detector";
-const WORKER_INITIALLZED: &str = "worker has finished initializing";
+impl From<ProtocolError> for WorkerError {
+    fn from(error: ProtocolError) -> Self {
+        match error {
+            ProtocolError::VersionMismatch { received } => {
+                WorkerError::VersionMismatch { received }
+            }
+            ProtocolError::Malformed(error) => WorkerError::BadParameters(error),
+        }
+    }
+}
 
-pub trait ToJsObject {
-    fn to_js_object(self) -> Object;
+/// a single step of the image processing pipeline along with its parameters
+/// the worker applies every step in order starting from the unmodified image
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Operation {
+    Invert,
+    Gamma(f64),
+    BoxBlur(u32),
+    SobelEdgeDetector(u32),
+    /// sigma is for the gaussian smoothing, the thresholds are on the gradient magnitude where a
+    /// hard black to white edge is 255
+    CannyEdgeDetector {
+        sigma: f64,
+        low_threshold: u32,
+        high_threshold: u32,
+    },
+    /// the parameter is sigma
+    GaussianBlur(f64),
+    /// radius is the sigma of the blur used for the mask
+    UnsharpMask {
+        amount: f64,
+        radius: f64,
+        threshold: u32,
+    },
+    /// hue is in degrees, saturation and lightness are percentages from -100 to 100
+    HueSaturationLightness {
+        hue: f64,
+        saturation: f64,
+        lightness: f64,
+    },
+    /// stops is the change in exposure value, highlight roll-off compresses bright areas instead
+    /// of clipping them
+    Exposure {
+        stops: f64,
+        highlight_rolloff: bool,
+    },
+    /// replaces the image with its log scaled magnitude spectrum or its phase
+    FrequencySpectrum {
+        phase: bool,
+    },
+    FrequencyFilter(FrequencyFilter),
+    /// leaves the pixels alone, the corners are sent separately and drawn on top of the image
+    CornerDetection(CornerDetector),
+    /// equalizes the luminance of the whole image at once
+    HistogramEqualization,
+    /// contrast limited adaptive histogram equalization, the image is split into a tile grid of
+    /// the given size on each side and clip limit is a multiple of the average bin count
+    Clahe {
+        tile_grid_size: u32,
+        clip_limit: f64,
+    },
+    /// turns the image black and white by its luminance
+    Threshold(Threshold),
 }
 
-pub struct NewImageMessage {
-    message: String,
-    image_data: Clamped<Vec<u8>>,
-    new_width: f64,
-    new_height: f64,
+/// a magic wand selection of the pixels in the original image with a color close to the seed
+/// pixel, x and y are in full resolution coordinates, tolerance is the largest difference allowed
+/// in any channel and feather is the radius the edge of the selection is softened by
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Selection {
+    pub x: u32,
+    pub y: u32,
+    pub tolerance: u32,
+    pub contiguous: bool,
+    pub feather: f64,
 }
 
-impl NewImageMessage {
-    pub fn new(
-        message: String,
-        image_data: Clamped<Vec<u8>>,
-        new_width: f64,
-        new_height: f64,
-    ) -> NewImageMessage {
-        NewImageMessage {
-            message,
-            image_data,
-            new_width,
-            new_height,
+impl Selection {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tolerance > u8::MAX as u32 {
+            return Err(format!(
+                "selection tolerance has to be at most {}, got {}",
+                u8::MAX,
+                self.tolerance
+            ));
+        }
+        if !(self.feather.is_finite() && self.feather >= 0.) {
+            return Err(format!(
+                "selection feather has to be a positive number, got {}",
+                self.feather
+            ));
         }
+        Ok(())
     }
 
-    pub fn js_clamped_uint8_array(&self) -> Uint8ClampedArray {
-        Uint8ClampedArray::from(self.image_data.0.as_ref())
+    /// the same selection on the image resized by the scale to the width and height, the seed is
+    /// kept inside the resized image since its sides are rounded separately
+    pub fn scaled(self, scale: f64, width: u32, height: u32) -> Selection {
+        Selection {
+            x: ((self.x as f64 * scale) as u32).min(width.saturating_sub(1)),
+            y: ((self.y as f64 * scale) as u32).min(height.saturating_sub(1)),
+            feather: self.feather * scale,
+            ..self
+        }
     }
-}
 
-impl ToJsObject for NewImageMessage {
-    fn to_js_object(self) -> Object {
-        let message = Object::new();
-        let raw_data = Uint8ClampedArray::from(self.image_data.0.as_ref());
-        Reflect::set(
-            &message,
-            &JsValue::from_str("image_data"),
-            &raw_data.buffer(),
-        )
-        .unwrap();
-        Reflect::set(
-            &message,
-            &JsValue::from_str("message"),
-            &JsValue::from_str(&self.message),
-        )
-        .unwrap();
-        Reflect::set(
-            &message,
-            &JsValue::from_str("new_width"),
-            &JsValue::from_f64(self.new_width),
-        )
-        .unwrap();
-        Reflect::set(
-            &message,
-            &JsValue::from_str("new_height"),
-            &JsValue::from_f64(self.new_height),
-        )
-        .unwrap();
-        message
+    /// the mask of the selected pixels from 0 to 1
+    pub fn mask(&self, image: &[u8], width: u32) -> Vec<f32> {
+        let mask = algorithms::select_color_range(
+            image,
+            width,
+            self.x,
+            self.y,
+            self.tolerance as u8,
+            self.contiguous,
+        );
+        algorithms::feather_mask(mask, width, self.feather as f32)
     }
 }
 
-pub struct GammaMessage {
-    message: String,
-    gamma: f64,
+/// which frequencies a frequency filter keeps
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FrequencyBand {
+    LowPass,
+    HighPass,
+    BandPass,
 }
 
-impl GammaMessage {
-    pub fn new(message: String, gamma: f64) -> GammaMessage {
-        GammaMessage { message, gamma }
-    }
+/// how sharply a frequency filter goes from keeping to removing frequencies
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FilterShape {
+    Ideal,
+    Butterworth,
+    Gaussian,
 }
 
-impl ToJsObject for GammaMessage {
-    fn to_js_object(self) -> Object {
-        let message = Object::new();
-        Reflect::set(
-            &message,
-            &JsValue::from_str("message"),
-            &JsValue::from_str(&self.message),
-        )
-        .unwrap();
-        Reflect::set(
-            &message,
-            &JsValue::from_str(Command::Gamma.to_string().as_ref()),
-            &JsValue::from_f64(self.gamma),
-        )
-        .unwrap();
-        message
-    }
+/// how the corner strength is computed from the structure tensor of a window
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CornerMethod {
+    /// det - k * trace², k is the sensitivity
+    Harris,
+    /// the smaller eigenvalue
+    ShiTomasi,
 }
 
-pub struct SobelEdgeDetectionMessage {
-    message: String,
-    threshold: u32,
+/// window size is the side of the window the gradients are summed over, corners closer than the
+/// min distance to a stronger corner are suppressed
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CornerDetector {
+    pub method: CornerMethod,
+    pub k: f64,
+    pub window_size: u32,
+    pub max_corners: u32,
+    pub min_distance: u32,
 }
 
-impl SobelEdgeDetectionMessage {
-    pub fn new(message: String, threshold: u32) -> SobelEdgeDetectionMessage {
-        SobelEdgeDetectionMessage { message, threshold }
-    }
+/// how the level a pixel's luminance is compared to is picked
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ThresholdMethod {
+    /// the level is set by hand
+    Manual,
+    /// the level that best separates the dark and bright pixels of the whole image
+    Otsu,
+    /// the mean of the block around each pixel
+    AdaptiveMean,
+    /// the gaussian weighted mean of the block around each pixel
+    AdaptiveGaussian,
 }
 
-impl ToJsObject for SobelEdgeDetectionMessage {
-    fn to_js_object(self) -> Object {
-        let message = Object::new();
-        Reflect::set(
-            &message,
-            &JsValue::from_str("message"),
-            &JsValue::from_str(&self.message),
-        )
-        .unwrap();
-        Reflect::set(
-            &message,
-            &JsValue::from_str(Command::SobelEdgeDetector.to_string().as_ref()),
-            &JsValue::from_f64(self.threshold as f64),
-        )
-        .unwrap();
-        message
-    }
+/// level is only used by manual thresholds, block size and offset only by adaptive ones, the
+/// offset is subtracted from the mean of the block so flat areas come out white
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Threshold {
+    pub method: ThresholdMethod,
+    pub level: u32,
+    pub block_size: u32,
+    pub offset: f64,
 }
 
-pub struct BoxBlurMessage {
-    message: String,
-    kernel_size: u32,
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Corner {
+    pub x: u32,
+    pub y: u32,
+    pub response: f32,
 }
 
-impl BoxBlurMessage {
-    pub fn new(message: String, kernel_size: u32) -> BoxBlurMessage {
-        BoxBlurMessage {
-            message,
-            kernel_size,
+/// cutoff and bandwidth are fractions of the highest horizontal or vertical frequency, order is
+/// only used by butterworth filters and bandwidth only by band-pass filters
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrequencyFilter {
+    pub band: FrequencyBand,
+    pub shape: FilterShape,
+    pub cutoff: f64,
+    pub bandwidth: f64,
+    pub order: u32,
+}
+
+impl Operation {
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Operation::Gamma(gamma) if !(gamma.is_finite() && gamma > 0.) => {
+                Err(format!("gamma has to be a positive number, got {}", gamma))
+            }
+            Operation::BoxBlur(kernel_size) if kernel_size % 2 == 0 => Err(format!(
+                "box blur kernel size has to be a positive odd number, got {}",
+                kernel_size
+            )),
+            Operation::SobelEdgeDetector(threshold) if threshold > u8::MAX as u32 => Err(format!(
+                "sobel edge detector threshold has to be at most {}, got {}",
+                u8::MAX,
+                threshold
+            )),
+            Operation::CannyEdgeDetector { sigma, .. } if !(sigma.is_finite() && sigma > 0.) => {
+                Err(format!(
+                    "canny edge detector sigma has to be a positive number, got {}",
+                    sigma
+                ))
+            }
+            Operation::CannyEdgeDetector { high_threshold, .. }
+                if high_threshold > u8::MAX as u32 =>
+            {
+                Err(format!(
+                    "canny edge detector high threshold has to be at most {}, got {}",
+                    u8::MAX,
+                    high_threshold
+                ))
+            }
+            // every pixel passes a low threshold of 0 so hysteresis would fill the whole image
+            Operation::CannyEdgeDetector { low_threshold, .. } if low_threshold == 0 => {
+                Err("canny edge detector low threshold has to be at least 1, got 0".to_string())
+            }
+            Operation::CannyEdgeDetector {
+                low_threshold,
+                high_threshold,
+                ..
+            } if low_threshold > high_threshold => Err(format!(
+                "canny edge detector low threshold has to be at most the high threshold {}, got {}",
+                high_threshold, low_threshold
+            )),
+            Operation::GaussianBlur(sigma) if !(sigma.is_finite() && sigma > 0.) => Err(format!(
+                "gaussian blur sigma has to be a positive number, got {}",
+                sigma
+            )),
+            Operation::UnsharpMask { amount, .. } if !(amount.is_finite() && amount >= 0.) => {
+                Err(format!(
+                    "unsharp mask amount has to be a positive number, got {}",
+                    amount
+                ))
+            }
+            Operation::UnsharpMask { radius, .. } if !(radius.is_finite() && radius > 0.) => {
+                Err(format!(
+                    "unsharp mask radius has to be a positive number, got {}",
+                    radius
+                ))
+            }
+            Operation::UnsharpMask { threshold, .. } if threshold > u8::MAX as u32 => Err(format!(
+                "unsharp mask threshold has to be at most {}, got {}",
+                u8::MAX,
+                threshold
+            )),
+            Operation::HueSaturationLightness { hue, .. } if !hue.is_finite() => Err(format!(
+                "hue has to be a finite number of degrees, got {}",
+                hue
+            )),
+            Operation::HueSaturationLightness { saturation, .. }
+                if !(-100. ..=100.).contains(&saturation) =>
+            {
+                Err(format!(
+                    "saturation has to be between -100 and 100, got {}",
+                    saturation
+                ))
+            }
+            Operation::HueSaturationLightness { lightness, .. }
+                if !(-100. ..=100.).contains(&lightness) =>
+            {
+                Err(format!(
+                    "lightness has to be between -100 and 100, got {}",
+                    lightness
+                ))
+            }
+            Operation::Exposure { stops, .. } if !(-3. ..=3.).contains(&stops) => Err(format!(
+                "exposure stops have to be between -3 and 3, got {}",
+                stops
+            )),
+            Operation::FrequencyFilter(FrequencyFilter { cutoff, .. })
+                if !(cutoff.is_finite() && cutoff > 0.) =>
+            {
+                Err(format!(
+                    "frequency filter cutoff has to be a positive number, got {}",
+                    cutoff
+                ))
+            }
+            Operation::FrequencyFilter(FrequencyFilter { bandwidth, .. })
+                if !(bandwidth.is_finite() && bandwidth > 0.) =>
+            {
+                Err(format!(
+                    "frequency filter bandwidth has to be a positive number, got {}",
+                    bandwidth
+                ))
+            }
+            Operation::FrequencyFilter(FrequencyFilter { order, .. }) if order == 0 => {
+                Err("frequency filter order has to be at least 1, got 0".to_string())
+            }
+            Operation::CornerDetection(CornerDetector { k, .. })
+                if !(k.is_finite() && k > 0. && k < 0.25) =>
+            {
+                Err(format!(
+                    "corner detection sensitivity has to be between 0 and 0.25, got {}",
+                    k
+                ))
+            }
+            Operation::CornerDetection(CornerDetector { window_size, .. })
+                if window_size < 3 || window_size % 2 == 0 =>
+            {
+                Err(format!(
+                    "corner detection window size has to be an odd number of at least 3, got {}",
+                    window_size
+                ))
+            }
+            Operation::CornerDetection(CornerDetector { max_corners, .. }) if max_corners == 0 => {
+                Err("corner detection has to find at least 1 corner, got 0".to_string())
+            }
+            Operation::Threshold(Threshold { level, .. }) if level > u8::MAX as u32 => {
+                Err(format!(
+                    "threshold level has to be at most {}, got {}",
+                    u8::MAX,
+                    level
+                ))
+            }
+            Operation::Threshold(Threshold { block_size, .. })
+                if block_size < 3 || block_size % 2 == 0 =>
+            {
+                Err(format!(
+                    "threshold block size has to be an odd number of at least 3, got {}",
+                    block_size
+                ))
+            }
+            Operation::Threshold(Threshold { offset, .. }) if !offset.is_finite() => Err(format!(
+                "threshold offset has to be a finite number, got {}",
+                offset
+            )),
+            Operation::Clahe { tile_grid_size, .. } if tile_grid_size == 0 => {
+                Err("clahe tile grid size has to be at least 1, got 0".to_string())
+            }
+            Operation::Clahe { clip_limit, .. }
+                if !(clip_limit.is_finite() && clip_limit >= 1.) =>
+            {
+                Err(format!(
+                    "clahe clip limit has to be a number of at least 1, got {}",
+                    clip_limit
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// the same operation for the image resized by the scale, sizes in pixels are scaled so a
+    /// downscaled preview looks like the full resolution result
+    pub fn scaled(self, scale: f64) -> Operation {
+        match self {
+            Operation::BoxBlur(kernel_size) => Operation::BoxBlur(scale_odd(kernel_size, scale, 1)),
+            Operation::GaussianBlur(sigma) => Operation::GaussianBlur(sigma * scale),
+            Operation::CannyEdgeDetector {
+                sigma,
+                low_threshold,
+                high_threshold,
+            } => Operation::CannyEdgeDetector {
+                sigma: sigma * scale,
+                low_threshold,
+                high_threshold,
+            },
+            Operation::UnsharpMask {
+                amount,
+                radius,
+                threshold,
+            } => Operation::UnsharpMask {
+                amount,
+                radius: radius * scale,
+                threshold,
+            },
+            Operation::CornerDetection(detector) => Operation::CornerDetection(CornerDetector {
+                window_size: scale_odd(detector.window_size, scale, 3),
+                min_distance: ((detector.min_distance as f64 * scale).round() as u32).max(1),
+                ..detector
+            }),
+            Operation::Threshold(threshold) => Operation::Threshold(Threshold {
+                block_size: scale_odd(threshold.block_size, scale, 3),
+                ..threshold
+            }),
+            operation => operation,
+        }
+    }
+
+    /// the level an otsu threshold step picks for its input, the worker reports it since it
+    /// isn't one of the step's parameters
+    pub fn otsu_level(&self, image: &[u8]) -> Option<u8> {
+        match self {
+            Operation::Threshold(Threshold {
+                method: ThresholdMethod::Otsu,
+                ..
+            }) => Some(algorithms::otsu_level(image)),
+            _ => None,
         }
     }
-}
 
-impl ToJsObject for BoxBlurMessage {
-    fn to_js_object(self) -> Object {
-        let message = Object::new();
-        Reflect::set(
-            &message,
-            &JsValue::from_str("message"),
-            &JsValue::from_str(&self.message),
-        )
-        .unwrap();
-        Reflect::set(
-            &message,
-            &JsValue::from_str(Command::BoxBlur.to_string().as_ref()),
-            &JsValue::from_f64(self.kernel_size as f64),
-        )
-        .unwrap();
-        message
+    pub fn apply(self, image: Vec<u8>, width: u32) -> Vec<u8> {
+        match self {
+            Operation::Invert => algorithms::invert(image, width),
+            Operation::Gamma(gamma) => algorithms::gamma_transform(image, width, gamma as f32),
+            Operation::BoxBlur(kernel_size) => algorithms::box_blur(image, width, kernel_size),
+            Operation::SobelEdgeDetector(threshold) => {
+                algorithms::sobel_edge_detection(image, width, threshold as u8)
+            }
+            Operation::CannyEdgeDetector {
+                sigma,
+                low_threshold,
+                high_threshold,
+            } => algorithms::canny_edge_detection(
+                image,
+                width,
+                sigma as f32,
+                low_threshold as f32,
+                high_threshold as f32,
+            ),
+            Operation::GaussianBlur(sigma) => algorithms::gaussian_blur(image, width, sigma as f32),
+            Operation::UnsharpMask {
+                amount,
+                radius,
+                threshold,
+            } => algorithms::unsharp_mask(
+                image,
+                width,
+                amount as f32,
+                radius as f32,
+                threshold as u8,
+            ),
+            Operation::HueSaturationLightness {
+                hue,
+                saturation,
+                lightness,
+            } => algorithms::hue_saturation_lightness(
+                image,
+                hue as f32,
+                saturation as f32,
+                lightness as f32,
+            ),
+            Operation::Exposure {
+                stops,
+                highlight_rolloff,
+            } => algorithms::exposure(image, stops as f32, highlight_rolloff),
+            Operation::FrequencySpectrum { phase } => {
+                algorithms::frequency_spectrum(image, width, phase)
+            }
+            Operation::FrequencyFilter(filter) => {
+                let (image, _) = algorithms::frequency_filter(image, width, &filter);
+                image
+            }
+            Operation::CornerDetection(_) => image,
+            Operation::HistogramEqualization => algorithms::equalize_histogram(image),
+            Operation::Clahe {
+                tile_grid_size,
+                clip_limit,
+            } => algorithms::clahe(image, width, tile_grid_size, clip_limit as f32),
+            Operation::Threshold(threshold) => match threshold.method {
+                ThresholdMethod::Manual => algorithms::threshold(image, threshold.level as u8),
+                ThresholdMethod::Otsu => {
+                    let level = algorithms::otsu_level(&image);
+                    algorithms::threshold(image, level)
+                }
+                ThresholdMethod::AdaptiveMean | ThresholdMethod::AdaptiveGaussian => {
+                    algorithms::adaptive_threshold(
+                        image,
+                        width,
+                        threshold.block_size,
+                        threshold.offset as f32,
+                        threshold.method == ThresholdMethod::AdaptiveGaussian,
+                    )
+                }
+            },
+        }
     }
 }
 
-pub struct InvertMessage {
-    message: String,
-    invert: bool,
+/// scales a kernel size while keeping it odd so the kernel still has a center
+fn scale_odd(size: u32, scale: f64, min: u32) -> u32 {
+    let size = (size as f64 * scale).round() as u32;
+    (size | 1).max(min)
 }
 
-impl InvertMessage {
-    pub fn new(message: String, invert: bool) -> InvertMessage {
-        InvertMessage { message, invert }
-    }
+/// what actually gets posted between the app and the worker, image pixels are kept out of the
+/// serialized message so their buffer can be transferred instead of copied
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    version: u32,
+    message: T,
+    #[serde(default, with = "serde_wasm_bindgen::preserve")]
+    buffer: JsValue,
 }
 
-impl ToJsObject for InvertMessage {
-    fn to_js_object(self) -> Object {
-        let message = Object::new();
-        Reflect::set(
-            &message,
-            &JsValue::from_str("message"),
-            &JsValue::from_str(self.message.as_str()),
-        )
-        .unwrap();
-        Reflect::set(
-            &message,
-            &JsValue::from_str(Command::Invert.to_string().as_ref()),
-            &JsValue::from_bool(self.invert),
-        )
-        .unwrap();
-        message
-    }
+/// only the version is read first so a mismatched worker is reported even when its messages
+/// don't parse anymore
+#[derive(Deserialize)]
+struct EnvelopeVersion {
+    version: Option<u32>,
 }
 
-pub enum WorkerResponseMessage {
-    Initialized,
-    DisplayOriginalImage,
-    Invert,
-    BoxBlur,
-    Gamma,
-    SobelEdgeDetector,
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtocolError {
+    VersionMismatch { received: Option<u32> },
+    Malformed(String),
+}
+
+impl Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::VersionMismatch {
+                received: Some(received),
+            } => write!(
+                f,
+                "Message protocol version {} does not match the expected version {}, try reloading the page",
+                received, PROTOCOL_VERSION
+            ),
+            ProtocolError::VersionMismatch { received: None } => write!(
+                f,
+                "Message has no protocol version, expected version {}, try reloading the page",
+                PROTOCOL_VERSION
+            ),
+            ProtocolError::Malformed(error) => write!(f, "Malformed message: {}", error),
+        }
+    }
 }
 
-impl FromStr for WorkerResponseMessage {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "worker has finished initializing" => Ok(Self::Initialized),
-            "original image" => Ok(Self::DisplayOriginalImage),
-            "invert" => Ok(Self::Invert),
-            "box blur" => Ok(Self::BoxBlur),
-            "gamma" => Ok(Self::Gamma),
-            "sobel edge detector" => Ok(Self::SobelEdgeDetector),
-            _ => Err(format!("Unsupported/Unknown command: {}", s)),
+pub trait WorkerProtocol: Serialize + DeserializeOwned {
+    /// returns the message and the list of buffers to transfer with `post_message_with_transfer`
+    fn encode(&self, buffer: Option<&[u8]>) -> (JsValue, Array) {
+        let transfer = Array::new();
+        let buffer = match buffer {
+            Some(buffer) => {
+                let buffer = Uint8ClampedArray::from(buffer).buffer();
+                transfer.push(&buffer);
+                JsValue::from(buffer)
+            }
+            None => JsValue::UNDEFINED,
+        };
+        let envelope = Envelope {
+            version: PROTOCOL_VERSION,
+            message: self,
+            buffer,
+        };
+        let message = serde_wasm_bindgen::to_value(&envelope)
+            .expect("protocol messages should always be serializable");
+
+        (message, transfer)
+    }
+
+    fn decode(data: JsValue) -> Result<(Self, Option<Vec<u8>>), ProtocolError> {
+        let EnvelopeVersion { version } = serde_wasm_bindgen::from_value(data.clone())
+            .map_err(|_| ProtocolError::VersionMismatch { received: None })?;
+        if version != Some(PROTOCOL_VERSION) {
+            return Err(ProtocolError::VersionMismatch { received: version });
         }
+
+        let envelope: Envelope<Self> = serde_wasm_bindgen::from_value(data)
+            .map_err(|error| ProtocolError::Malformed(error.to_string()))?;
+        let buffer = envelope
+            .buffer
+            .dyn_ref::<ArrayBuffer>()
+            .map(|buffer| Uint8ClampedArray::new(buffer).to_vec());
+
+        Ok((envelope.message, buffer))
     }
 }
 
-impl Display for WorkerResponseMessage {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let str = match self {
-            WorkerResponseMessage::Initialized => WORKER_INITIALLZED,
-            WorkerResponseMessage::Invert => INVERT,
-            WorkerResponseMessage::BoxBlur => BOX_BLUR,
-            WorkerResponseMessage::Gamma => GAMMA,
-            WorkerResponseMessage::SobelEdgeDetector => SOBEL_EDGE_DETECTOR,
-            WorkerResponseMessage::DisplayOriginalImage => "original image",
+impl WorkerProtocol for Command {}
+impl WorkerProtocol for WorkerResponseMessage {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_selection_stays_inside_the_proxy() {
+        let selection = Selection {
+            x: 2999,
+            y: 1999,
+            tolerance: 10,
+            contiguous: true,
+            feather: 2.,
         };
+        // the proxy sides are rounded separately so the seed can end up one past the edge
+        let scaled = selection.scaled(0.5, 1500, 999);
+        assert_eq!((scaled.x, scaled.y), (1499, 998));
+        assert_eq!(scaled.feather, 1.);
+    }
 
-        write!(f, "{}", str)
+    #[test]
+    fn canny_thresholds_are_validated() {
+        let canny = |low_threshold, high_threshold| Operation::CannyEdgeDetector {
+            sigma: 1.4,
+            low_threshold,
+            high_threshold,
+        };
+        assert!(canny(20, 50).validate().is_ok());
+        assert!(canny(0, 50).validate().is_err());
+        assert!(canny(60, 50).validate().is_err());
+        assert!(canny(20, 300).validate().is_err());
     }
 }
@@ -1,42 +1,651 @@
-use leptos::{create_rw_signal, leptos_dom::Text, IntoView, RwSignal, SignalSet, View};
+use std::collections::VecDeque;
 
-pub struct AlgorithmInputState {
-    gamma: RwSignal<f64>,
-    invert: RwSignal<bool>,
-    box_blur_amount: RwSignal<u32>,
-    sobel_edge_detector_threshold: RwSignal<u32>,
+use leptos::{
+    batch, create_rw_signal, leptos_dom::Text, store_value, IntoView, RwSignal, SignalGet,
+    SignalGetUntracked, SignalSet, SignalUpdate, SignalWith, StoredValue, View,
+};
+use shared::{
+    CornerDetector, CornerMethod, FilterShape, FrequencyBand, FrequencyFilter, Operation,
+    Selection, Threshold, ThresholdMethod,
+};
+
+/// the max amount of edits that can be undone, the history only stores step parameters and never
+/// image buffers so even large images keep the memory usage small
+const MAX_HISTORY_LENGTH: usize = 100;
+
+/// the input state for a single pipeline step, every step owns its own signals so the same
+/// algorithm can be added more than once with different values
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StepParameters {
+    Gamma(RwSignal<f64>),
+    Invert,
+    BoxBlur(RwSignal<u32>),
+    SobelEdgeDetector(RwSignal<u32>),
+    CannyEdgeDetector {
+        sigma: RwSignal<f64>,
+        low_threshold: RwSignal<u32>,
+        high_threshold: RwSignal<u32>,
+    },
+    GaussianBlur(RwSignal<f64>),
+    UnsharpMask {
+        amount: RwSignal<f64>,
+        radius: RwSignal<f64>,
+        threshold: RwSignal<u32>,
+    },
+    HueSaturationLightness {
+        hue: RwSignal<f64>,
+        saturation: RwSignal<f64>,
+        lightness: RwSignal<f64>,
+    },
+    Exposure {
+        stops: RwSignal<f64>,
+        highlight_rolloff: RwSignal<bool>,
+    },
+    FrequencySpectrum {
+        phase: RwSignal<bool>,
+    },
+    FrequencyFilter {
+        band: RwSignal<FrequencyBand>,
+        shape: RwSignal<FilterShape>,
+        cutoff: RwSignal<f64>,
+        bandwidth: RwSignal<f64>,
+        order: RwSignal<u32>,
+    },
+    CornerDetection {
+        method: RwSignal<CornerMethod>,
+        k: RwSignal<f64>,
+        window_size: RwSignal<u32>,
+        max_corners: RwSignal<u32>,
+        min_distance: RwSignal<u32>,
+    },
+    HistogramEqualization,
+    Clahe {
+        tile_grid_size: RwSignal<u32>,
+        clip_limit: RwSignal<f64>,
+    },
+    Threshold {
+        method: RwSignal<ThresholdMethod>,
+        level: RwSignal<u32>,
+        block_size: RwSignal<u32>,
+        offset: RwSignal<f64>,
+    },
+}
+
+impl StepParameters {
+    fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Gamma => StepParameters::Gamma(create_rw_signal(1.)),
+            Algorithm::Invert => StepParameters::Invert,
+            Algorithm::BoxBlur => StepParameters::BoxBlur(create_rw_signal(1u32)),
+            Algorithm::SobelEdgeDetector => {
+                StepParameters::SobelEdgeDetector(create_rw_signal(128u32))
+            }
+            Algorithm::CannyEdgeDetector => StepParameters::CannyEdgeDetector {
+                sigma: create_rw_signal(1.4),
+                low_threshold: create_rw_signal(20u32),
+                high_threshold: create_rw_signal(50u32),
+            },
+            Algorithm::GaussianBlur => StepParameters::GaussianBlur(create_rw_signal(2.)),
+            Algorithm::UnsharpMask => StepParameters::UnsharpMask {
+                amount: create_rw_signal(1.),
+                radius: create_rw_signal(2.),
+                threshold: create_rw_signal(0u32),
+            },
+            Algorithm::HueSaturationLightness => StepParameters::HueSaturationLightness {
+                hue: create_rw_signal(0.),
+                saturation: create_rw_signal(0.),
+                lightness: create_rw_signal(0.),
+            },
+            Algorithm::Exposure => StepParameters::Exposure {
+                stops: create_rw_signal(0.),
+                highlight_rolloff: create_rw_signal(true),
+            },
+            Algorithm::FrequencySpectrum => StepParameters::FrequencySpectrum {
+                phase: create_rw_signal(false),
+            },
+            Algorithm::FrequencyFilter => StepParameters::FrequencyFilter {
+                band: create_rw_signal(FrequencyBand::LowPass),
+                shape: create_rw_signal(FilterShape::Gaussian),
+                cutoff: create_rw_signal(0.1),
+                bandwidth: create_rw_signal(0.1),
+                order: create_rw_signal(2u32),
+            },
+            Algorithm::CornerDetection => StepParameters::CornerDetection {
+                method: create_rw_signal(CornerMethod::Harris),
+                k: create_rw_signal(0.04),
+                window_size: create_rw_signal(5u32),
+                max_corners: create_rw_signal(100u32),
+                min_distance: create_rw_signal(10u32),
+            },
+            Algorithm::HistogramEqualization => StepParameters::HistogramEqualization,
+            Algorithm::Clahe => StepParameters::Clahe {
+                tile_grid_size: create_rw_signal(8u32),
+                clip_limit: create_rw_signal(2.),
+            },
+            Algorithm::Threshold => StepParameters::Threshold {
+                method: create_rw_signal(ThresholdMethod::Otsu),
+                level: create_rw_signal(128u32),
+                block_size: create_rw_signal(15u32),
+                offset: create_rw_signal(5.),
+            },
+        }
+    }
+
+    fn set(&self, operation: Operation) {
+        match (self, operation) {
+            (StepParameters::Gamma(gamma), Operation::Gamma(value)) => gamma.set(value),
+            (StepParameters::BoxBlur(kernel_size), Operation::BoxBlur(value)) => {
+                kernel_size.set(value)
+            }
+            (StepParameters::SobelEdgeDetector(threshold), Operation::SobelEdgeDetector(value)) => {
+                threshold.set(value)
+            }
+            (StepParameters::GaussianBlur(sigma), Operation::GaussianBlur(value)) => {
+                sigma.set(value)
+            }
+            (
+                StepParameters::CannyEdgeDetector {
+                    sigma,
+                    low_threshold,
+                    high_threshold,
+                },
+                Operation::CannyEdgeDetector {
+                    sigma: sigma_value,
+                    low_threshold: low_threshold_value,
+                    high_threshold: high_threshold_value,
+                },
+            ) => {
+                sigma.set(sigma_value);
+                low_threshold.set(low_threshold_value);
+                high_threshold.set(high_threshold_value);
+            }
+            (
+                StepParameters::UnsharpMask {
+                    amount,
+                    radius,
+                    threshold,
+                },
+                Operation::UnsharpMask {
+                    amount: amount_value,
+                    radius: radius_value,
+                    threshold: threshold_value,
+                },
+            ) => {
+                amount.set(amount_value);
+                radius.set(radius_value);
+                threshold.set(threshold_value);
+            }
+            (
+                StepParameters::HueSaturationLightness {
+                    hue,
+                    saturation,
+                    lightness,
+                },
+                Operation::HueSaturationLightness {
+                    hue: hue_value,
+                    saturation: saturation_value,
+                    lightness: lightness_value,
+                },
+            ) => {
+                hue.set(hue_value);
+                saturation.set(saturation_value);
+                lightness.set(lightness_value);
+            }
+            (
+                StepParameters::Exposure {
+                    stops,
+                    highlight_rolloff,
+                },
+                Operation::Exposure {
+                    stops: stops_value,
+                    highlight_rolloff: highlight_rolloff_value,
+                },
+            ) => {
+                stops.set(stops_value);
+                highlight_rolloff.set(highlight_rolloff_value);
+            }
+            (
+                StepParameters::FrequencySpectrum { phase },
+                Operation::FrequencySpectrum { phase: phase_value },
+            ) => phase.set(phase_value),
+            (
+                StepParameters::FrequencyFilter {
+                    band,
+                    shape,
+                    cutoff,
+                    bandwidth,
+                    order,
+                },
+                Operation::FrequencyFilter(filter),
+            ) => {
+                band.set(filter.band);
+                shape.set(filter.shape);
+                cutoff.set(filter.cutoff);
+                bandwidth.set(filter.bandwidth);
+                order.set(filter.order);
+            }
+            (
+                StepParameters::CornerDetection {
+                    method,
+                    k,
+                    window_size,
+                    max_corners,
+                    min_distance,
+                },
+                Operation::CornerDetection(detector),
+            ) => {
+                method.set(detector.method);
+                k.set(detector.k);
+                window_size.set(detector.window_size);
+                max_corners.set(detector.max_corners);
+                min_distance.set(detector.min_distance);
+            }
+            (
+                StepParameters::Clahe {
+                    tile_grid_size,
+                    clip_limit,
+                },
+                Operation::Clahe {
+                    tile_grid_size: tile_grid_size_value,
+                    clip_limit: clip_limit_value,
+                },
+            ) => {
+                tile_grid_size.set(tile_grid_size_value);
+                clip_limit.set(clip_limit_value);
+            }
+            (
+                StepParameters::Threshold {
+                    method,
+                    level,
+                    block_size,
+                    offset,
+                },
+                Operation::Threshold(threshold),
+            ) => {
+                method.set(threshold.method);
+                level.set(threshold.level);
+                block_size.set(threshold.block_size);
+                offset.set(threshold.offset);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PipelineStep {
+    id: usize,
+    algorithm: Algorithm,
+    enabled: RwSignal<bool>,
+    parameters: StepParameters,
+}
+
+impl PipelineStep {
+    fn new(id: usize, algorithm: Algorithm) -> Self {
+        Self {
+            id,
+            algorithm,
+            enabled: create_rw_signal(true),
+            parameters: StepParameters::new(algorithm),
+        }
+    }
+
+    fn from_snapshot(snapshot: &StepSnapshot) -> Self {
+        let step = Self::new(snapshot.id, snapshot.algorithm);
+        step.restore(snapshot);
+        step
+    }
+
+    fn restore(&self, snapshot: &StepSnapshot) {
+        self.enabled.set(snapshot.enabled);
+        self.parameters.set(snapshot.operation);
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+    pub fn enabled(&self) -> RwSignal<bool> {
+        self.enabled
+    }
+    pub fn parameters(&self) -> StepParameters {
+        self.parameters
+    }
+
+    /// reads the current parameters of this step, this is tracked so it can be used in effects
+    pub fn operation(&self) -> Operation {
+        match self.parameters {
+            StepParameters::Gamma(gamma) => Operation::Gamma(gamma.get()),
+            StepParameters::Invert => Operation::Invert,
+            StepParameters::BoxBlur(kernel_size) => Operation::BoxBlur(kernel_size.get()),
+            StepParameters::SobelEdgeDetector(threshold) => {
+                Operation::SobelEdgeDetector(threshold.get())
+            }
+            StepParameters::GaussianBlur(sigma) => Operation::GaussianBlur(sigma.get()),
+            StepParameters::CannyEdgeDetector {
+                sigma,
+                low_threshold,
+                high_threshold,
+            } => Operation::CannyEdgeDetector {
+                sigma: sigma.get(),
+                low_threshold: low_threshold.get(),
+                high_threshold: high_threshold.get(),
+            },
+            StepParameters::UnsharpMask {
+                amount,
+                radius,
+                threshold,
+            } => Operation::UnsharpMask {
+                amount: amount.get(),
+                radius: radius.get(),
+                threshold: threshold.get(),
+            },
+            StepParameters::HueSaturationLightness {
+                hue,
+                saturation,
+                lightness,
+            } => Operation::HueSaturationLightness {
+                hue: hue.get(),
+                saturation: saturation.get(),
+                lightness: lightness.get(),
+            },
+            StepParameters::Exposure {
+                stops,
+                highlight_rolloff,
+            } => Operation::Exposure {
+                stops: stops.get(),
+                highlight_rolloff: highlight_rolloff.get(),
+            },
+            StepParameters::FrequencySpectrum { phase } => {
+                Operation::FrequencySpectrum { phase: phase.get() }
+            }
+            StepParameters::FrequencyFilter {
+                band,
+                shape,
+                cutoff,
+                bandwidth,
+                order,
+            } => Operation::FrequencyFilter(FrequencyFilter {
+                band: band.get(),
+                shape: shape.get(),
+                cutoff: cutoff.get(),
+                bandwidth: bandwidth.get(),
+                order: order.get(),
+            }),
+            StepParameters::CornerDetection {
+                method,
+                k,
+                window_size,
+                max_corners,
+                min_distance,
+            } => Operation::CornerDetection(CornerDetector {
+                method: method.get(),
+                k: k.get(),
+                window_size: window_size.get(),
+                max_corners: max_corners.get(),
+                min_distance: min_distance.get(),
+            }),
+            StepParameters::HistogramEqualization => Operation::HistogramEqualization,
+            StepParameters::Clahe {
+                tile_grid_size,
+                clip_limit,
+            } => Operation::Clahe {
+                tile_grid_size: tile_grid_size.get(),
+                clip_limit: clip_limit.get(),
+            },
+            StepParameters::Threshold {
+                method,
+                level,
+                block_size,
+                offset,
+            } => Operation::Threshold(Threshold {
+                method: method.get(),
+                level: level.get(),
+                block_size: block_size.get(),
+                offset: offset.get(),
+            }),
+        }
+    }
+
+    fn snapshot(&self) -> StepSnapshot {
+        StepSnapshot {
+            id: self.id,
+            algorithm: self.algorithm,
+            enabled: self.enabled.get(),
+            operation: self.operation(),
+        }
+    }
 }
 
-impl Default for AlgorithmInputState {
+/// a plain copy of a pipeline step's values so it can be restored from the history
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StepSnapshot {
+    id: usize,
+    algorithm: Algorithm,
+    enabled: bool,
+    operation: Operation,
+}
+
+/// the ordered list of steps the worker applies to the unmodified image
+#[derive(Copy, Clone)]
+pub struct Pipeline {
+    steps: RwSignal<Vec<PipelineStep>>,
+    selected: RwSignal<Option<usize>>,
+    next_id: StoredValue<usize>,
+}
+
+impl Default for Pipeline {
     fn default() -> Self {
         Self {
-            gamma: create_rw_signal(1.),
-            invert: create_rw_signal(false),
-            box_blur_amount: create_rw_signal(1u32),
-            sobel_edge_detector_threshold: create_rw_signal(128u32),
+            steps: create_rw_signal(Vec::new()),
+            selected: create_rw_signal(None),
+            next_id: store_value(0),
         }
     }
 }
 
-impl AlgorithmInputState {
-    pub fn gamma(&self) -> RwSignal<f64> {
-        self.gamma
+impl Pipeline {
+    pub fn steps(&self) -> RwSignal<Vec<PipelineStep>> {
+        self.steps
+    }
+    pub fn selected(&self) -> RwSignal<Option<usize>> {
+        self.selected
+    }
+
+    pub fn selected_step(&self) -> Option<PipelineStep> {
+        let id = self.selected.get()?;
+        self.steps
+            .with(|steps| steps.iter().find(|step| step.id == id).copied())
+    }
+
+    pub fn add(&self, algorithm: Algorithm) {
+        let id = self.next_id.get_value();
+        self.next_id.set_value(id + 1);
+        self.steps
+            .update(|steps| steps.push(PipelineStep::new(id, algorithm)));
+        self.selected.set(Some(id));
     }
-    pub fn invert(&self) -> RwSignal<bool> {
-        self.invert
+
+    pub fn remove(&self, id: usize) {
+        self.steps
+            .update(|steps| steps.retain(|step| step.id != id));
+        if self.selected.get_untracked() == Some(id) {
+            self.selected.set(None);
+        }
     }
-    pub fn box_blur_amount(&self) -> RwSignal<u32> {
-        self.box_blur_amount
+
+    pub fn move_up(&self, id: usize) {
+        self.steps.update(|steps| {
+            if let Some(index) = steps.iter().position(|step| step.id == id) {
+                if index > 0 {
+                    steps.swap(index, index - 1);
+                }
+            }
+        });
     }
-    pub fn sobel_edge_detector_threshold(&self) -> RwSignal<u32> {
-        self.sobel_edge_detector_threshold
+
+    pub fn move_down(&self, id: usize) {
+        self.steps.update(|steps| {
+            if let Some(index) = steps.iter().position(|step| step.id == id) {
+                if index + 1 < steps.len() {
+                    steps.swap(index, index + 1);
+                }
+            }
+        });
+    }
+
+    pub fn clear(&self) {
+        self.steps.set(Vec::new());
+        self.selected.set(None);
+    }
+
+    /// the values of every step including disabled ones, this is tracked so the history can
+    /// record every edit
+    pub fn snapshot(&self) -> Vec<StepSnapshot> {
+        self.steps
+            .with(|steps| steps.iter().map(PipelineStep::snapshot).collect())
+    }
+
+    /// replaces the steps with the snapshot, steps that still exist keep their signals so any
+    /// open controls are updated in place
+    pub fn restore(&self, snapshot: &[StepSnapshot]) {
+        batch(|| {
+            let current_steps = self.steps.get_untracked();
+            let steps = snapshot
+                .iter()
+                .map(|step_snapshot| {
+                    match current_steps
+                        .iter()
+                        .find(|step| step.id == step_snapshot.id)
+                    {
+                        Some(step) => {
+                            step.restore(step_snapshot);
+                            *step
+                        }
+                        None => PipelineStep::from_snapshot(step_snapshot),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let selected = self.selected.get_untracked();
+            if !steps.iter().any(|step| Some(step.id) == selected) {
+                self.selected.set(None);
+            }
+            self.steps.set(steps);
+        });
+    }
+
+    pub fn enabled_algorithms(&self) -> Vec<Algorithm> {
+        self.steps.with(|steps| {
+            steps
+                .iter()
+                .filter(|step| step.enabled.get())
+                .map(|step| step.algorithm)
+                .collect()
+        })
     }
 
-    pub fn reset(&self) {
-        self.invert.set(false);
-        self.box_blur_amount.set(1);
-        self.gamma.set(1.);
-        self.sobel_edge_detector_threshold.set(128)
+    /// the index of an enabled step in the operations sent to the worker, the worker refers to
+    /// steps by this index
+    pub fn operation_index(&self, id: usize) -> Option<usize> {
+        self.steps.with(|steps| {
+            steps
+                .iter()
+                .filter(|step| step.enabled.get())
+                .position(|step| step.id == id)
+        })
+    }
+
+    /// the operations of every enabled step in order, this is tracked so the pipeline gets
+    /// sent to the worker again whenever a step or one of its parameters changes
+    pub fn operations(&self) -> Vec<Operation> {
+        self.steps.with(|steps| {
+            steps
+                .iter()
+                .filter(|step| step.enabled.get())
+                .map(|step| step.operation())
+                .collect()
+        })
+    }
+}
+
+/// undo and redo stacks of pipeline snapshots, undoing restores the pipeline which sends it to the
+/// worker to render that state again
+#[derive(Copy, Clone)]
+pub struct History {
+    undo_stack: RwSignal<VecDeque<Vec<StepSnapshot>>>,
+    redo_stack: RwSignal<Vec<Vec<StepSnapshot>>>,
+    current: StoredValue<Vec<StepSnapshot>>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            undo_stack: create_rw_signal(VecDeque::new()),
+            redo_stack: create_rw_signal(Vec::new()),
+            current: store_value(Vec::new()),
+        }
+    }
+}
+
+impl History {
+    pub fn can_undo(&self) -> bool {
+        self.undo_stack.with(|stack| !stack.is_empty())
+    }
+    pub fn can_redo(&self) -> bool {
+        self.redo_stack.with(|stack| !stack.is_empty())
+    }
+
+    /// records the snapshot as a new edit unless it matches the current state, which is the case
+    /// when the pipeline changes because of an undo or redo
+    pub fn record(&self, snapshot: Vec<StepSnapshot>) {
+        let previous = self.current.get_value();
+        if previous == snapshot {
+            return;
+        }
+
+        self.undo_stack.update(|stack| {
+            stack.push_back(previous);
+            if stack.len() > MAX_HISTORY_LENGTH {
+                stack.pop_front();
+            }
+        });
+        self.redo_stack.update(Vec::clear);
+        self.current.set_value(snapshot);
+    }
+
+    pub fn undo(&self, pipeline: Pipeline) {
+        let mut previous = None;
+        self.undo_stack.update(|stack| previous = stack.pop_back());
+        let Some(previous) = previous else {
+            return;
+        };
+
+        let current = self.current.get_value();
+        self.redo_stack.update(|stack| stack.push(current));
+        self.current.set_value(previous.clone());
+        pipeline.restore(&previous);
+    }
+
+    pub fn redo(&self, pipeline: Pipeline) {
+        let mut next = None;
+        self.redo_stack.update(|stack| next = stack.pop());
+        let Some(next) = next else {
+            return;
+        };
+
+        let current = self.current.get_value();
+        self.undo_stack.update(|stack| stack.push_back(current));
+        self.current.set_value(next.clone());
+        pipeline.restore(&next);
+    }
+
+    pub fn clear(&self) {
+        self.undo_stack.update(VecDeque::clear);
+        self.redo_stack.update(Vec::clear);
+        self.current.set_value(Vec::new());
     }
 }
 
@@ -46,6 +655,17 @@ pub enum Algorithm {
     Invert,
     BoxBlur,
     SobelEdgeDetector,
+    CannyEdgeDetector,
+    GaussianBlur,
+    UnsharpMask,
+    HueSaturationLightness,
+    Exposure,
+    FrequencySpectrum,
+    FrequencyFilter,
+    CornerDetection,
+    HistogramEqualization,
+    Clahe,
+    Threshold,
 }
 
 impl std::fmt::Display for Algorithm {
@@ -55,6 +675,17 @@ impl std::fmt::Display for Algorithm {
             Algorithm::Invert => "invert",
             Algorithm::BoxBlur => "box blur",
             Algorithm::SobelEdgeDetector => "sobel edge detector",
+            Algorithm::CannyEdgeDetector => "canny edge detector",
+            Algorithm::GaussianBlur => "gaussian blur",
+            Algorithm::UnsharpMask => "unsharp mask",
+            Algorithm::HueSaturationLightness => "hue/saturation/lightness",
+            Algorithm::Exposure => "exposure",
+            Algorithm::FrequencySpectrum => "frequency spectrum",
+            Algorithm::FrequencyFilter => "frequency filter",
+            Algorithm::CornerDetection => "corner detection",
+            Algorithm::HistogramEqualization => "histogram equalization",
+            Algorithm::Clahe => "clahe",
+            Algorithm::Threshold => "threshold",
         };
         write!(f, "{}", text)
     }
@@ -65,3 +696,168 @@ impl IntoView for Algorithm {
         View::Text(Text::new(self.to_string().into()))
     }
 }
+
+/// a pixel picked from the canvas, x and y are the position in the original image since the
+/// processed image can be a downscaled preview
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PixelSample {
+    pub x: u32,
+    pub y: u32,
+    pub original: [u8; 4],
+    pub processed: [u8; 4],
+}
+
+/// what clicking on the canvas does
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CanvasTool {
+    /// pins the pixel under the pointer in the pixel inspector
+    Inspect,
+    /// selects the pixels with a color close to the clicked pixel
+    MagicWand,
+}
+
+/// the magic wand selection, the settings stay when the selection is cleared so the next click
+/// uses them too
+#[derive(Copy, Clone)]
+pub struct SelectionState {
+    seed: RwSignal<Option<(u32, u32)>>,
+    tolerance: RwSignal<u32>,
+    contiguous: RwSignal<bool>,
+    feather: RwSignal<f64>,
+}
+
+impl Default for SelectionState {
+    fn default() -> Self {
+        Self {
+            seed: create_rw_signal(None),
+            tolerance: create_rw_signal(32),
+            contiguous: create_rw_signal(true),
+            feather: create_rw_signal(0.),
+        }
+    }
+}
+
+impl SelectionState {
+    pub fn seed(&self) -> RwSignal<Option<(u32, u32)>> {
+        self.seed
+    }
+
+    pub fn tolerance(&self) -> RwSignal<u32> {
+        self.tolerance
+    }
+
+    pub fn contiguous(&self) -> RwSignal<bool> {
+        self.contiguous
+    }
+
+    pub fn feather(&self) -> RwSignal<f64> {
+        self.feather
+    }
+
+    /// the selection to send to the worker, this is tracked so changing any setting rerenders
+    pub fn selection(&self) -> Option<Selection> {
+        self.seed.get().map(|(x, y)| Selection {
+            x,
+            y,
+            tolerance: self.tolerance.get(),
+            contiguous: self.contiguous.get(),
+            feather: self.feather.get(),
+        })
+    }
+
+    pub fn clear(&self) {
+        self.seed.set(None);
+    }
+}
+
+/// how the original image is shown next to the processed image on the canvas
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CompareMode {
+    /// only the processed image
+    Off,
+    /// the original left of a draggable divider and the processed image right of it
+    Split,
+    /// the original and the processed image next to each other
+    SideBySide,
+}
+
+#[derive(Copy, Clone)]
+pub struct Comparison {
+    mode: RwSignal<CompareMode>,
+    /// where the divider is as a fraction of the image's width
+    divider: RwSignal<f64>,
+    /// set while the show original button or shortcut is held down
+    showing_original: RwSignal<bool>,
+}
+
+impl Default for Comparison {
+    fn default() -> Self {
+        Self {
+            mode: create_rw_signal(CompareMode::Off),
+            divider: create_rw_signal(0.5),
+            showing_original: create_rw_signal(false),
+        }
+    }
+}
+
+impl Comparison {
+    pub fn mode(&self) -> RwSignal<CompareMode> {
+        self.mode
+    }
+
+    pub fn divider(&self) -> RwSignal<f64> {
+        self.divider
+    }
+
+    pub fn showing_original(&self) -> RwSignal<bool> {
+        self.showing_original
+    }
+}
+
+/// the zoom and pan of the selected image canvas, the same point of the image stays in the middle
+/// of the canvas when it is resized
+#[derive(Copy, Clone)]
+pub struct Viewport {
+    /// canvas pixels per original image pixel, none fits the image in the canvas
+    zoom: RwSignal<Option<f64>>,
+    /// the point of the image in the middle of the canvas as a fraction of the image's size
+    center: RwSignal<(f64, f64)>,
+    /// the zoom the image was last drawn at, this is known even when the image is fitted
+    displayed_zoom: RwSignal<f64>,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            zoom: create_rw_signal(None),
+            center: create_rw_signal((0.5, 0.5)),
+            displayed_zoom: create_rw_signal(1.),
+        }
+    }
+}
+
+impl Viewport {
+    pub fn zoom(&self) -> RwSignal<Option<f64>> {
+        self.zoom
+    }
+
+    pub fn center(&self) -> RwSignal<(f64, f64)> {
+        self.center
+    }
+
+    pub fn displayed_zoom(&self) -> RwSignal<f64> {
+        self.displayed_zoom
+    }
+
+    pub fn fit(&self) {
+        batch(|| {
+            self.zoom.set(None);
+            self.center.set((0.5, 0.5));
+        });
+    }
+
+    /// one image pixel for every canvas pixel around the point that is in the middle right now
+    pub fn actual_size(&self) {
+        self.zoom.set(Some(1.));
+    }
+}
@@ -1,12 +1,18 @@
-use std::{
-    str::FromStr,
-    sync::{LazyLock, Mutex},
-};
+use std::io::Cursor;
+use std::sync::{LazyLock, Mutex};
 
-use shared::{algorithms, Command, WorkerResponseMessage};
+use image::buffer::ConvertBuffer;
+use image::codecs::{jpeg::JpegEncoder, png::PngEncoder};
+use image::imageops::{self, FilterType};
+use image::io::Reader;
+use image::{
+    ColorType, ImageBuffer, ImageError, ImageFormat, ImageResult, RgbImage, Rgba, RgbaImage,
+};
+use shared::{
+    algorithms, exif, Command, Corner, ExportFormat, ImageMetadata, Operation, Selection,
+    WorkerError, WorkerProtocol, WorkerResponseMessage,
+};
 
-use js_sys::{Array, ArrayBuffer, Boolean, Number, Object, Reflect, Uint8ClampedArray};
-use log::info;
 use wasm_bindgen::{prelude::*, JsCast};
 use web_sys::{DedicatedWorkerGlobalScope, MessageEvent};
 
@@ -15,11 +21,48 @@ use web_sys::{DedicatedWorkerGlobalScope, MessageEvent};
 static UNMODIFIED_IMAGE: LazyLock<Mutex<RawImage>> =
     LazyLock::new(|| Mutex::new(RawImage::new(Vec::new(), 0)));
 
+/// a downscaled copy of the unmodified image that previews are rendered on, none when the image
+/// is already small enough to process at full resolution
+static PROXY_IMAGE: LazyLock<Mutex<Option<RawImage>>> = LazyLock::new(|| Mutex::new(None));
+
 /// the max length the largest dimension on image will be
 /// the image will be resized using this as the max any dimension can be
 /// to save on computation when processing the images
 const MAX_PIXEL_LENGTH: u32 = 1500;
 
+/// only the latest pipeline command is kept, commands that arrive while the worker is busy
+/// replace the queued one so dragging a slider never leaves a backlog of stale previews
+static PENDING_PIPELINE: LazyLock<Mutex<Option<Command>>> = LazyLock::new(|| Mutex::new(None));
+
+/// the pipeline that was last previewed on the proxy image, it is rendered at full resolution
+/// once no other pipeline has come in for a while
+static PENDING_FULL_RESOLUTION: LazyLock<Mutex<Option<Command>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+/// the timeout that renders the pending full resolution pipeline, it is cancelled whenever a
+/// new pipeline comes in so only the last one of a slider drag gets rendered
+static FULL_RESOLUTION_TIMEOUT: LazyLock<Mutex<Option<i32>>> = LazyLock::new(|| Mutex::new(None));
+
+/// how long the pipeline has to stay the same before it is rendered at full resolution
+const FULL_RESOLUTION_DELAY_MS: i32 = 400;
+
+/// images with more pixels than this are rejected instead of running the worker out of memory
+const MAX_IMAGE_PIXELS: u32 = 4096 * 4096;
+
+/// the most bytes a pixel takes while a full resolution pipeline runs, the loaded image, the
+/// image being processed, the copy a selection blends with and its mask, and the two spectra and
+/// the preview of a frequency filter step
+const PEAK_BYTES_PER_PIXEL: u64 = 4 + 4 + 4 + 4 + 8 + 8 + 4;
+
+/// how much of the 4 GiB a wasm worker can address a pipeline may use, the rest is left for
+/// decoding, encoding exports and the allocator
+const PIPELINE_MEMORY_BUDGET: u64 = 1 << 30;
+
+const _: () = assert!(
+    MAX_IMAGE_PIXELS as u64 * PEAK_BYTES_PER_PIXEL <= PIPELINE_MEMORY_BUDGET,
+    "the largest image has to fit the pipeline memory budget"
+);
+
 #[derive(Clone, Debug)]
 pub struct RawImage {
     /// an image has 4 components, red, green, blue, alpha each represented by one byte/one
@@ -60,295 +103,463 @@ fn main() {
     )));
     let scope_clone = scope.clone();
 
-    let on_message = Closure::wrap(Box::new(move |msg: MessageEvent| {
-        web_sys::console::log_1(&"Worker received message".into());
+    let full_resolution_scope = scope.clone();
+    let run_full_resolution = Closure::wrap(Box::new(move || {
+        *FULL_RESOLUTION_TIMEOUT.lock().unwrap() = None;
+        // a newer pipeline is about to be previewed and will schedule its own render
+        if PENDING_PIPELINE.lock().unwrap().is_some() {
+            return;
+        }
+        let command = PENDING_FULL_RESOLUTION.lock().unwrap().take();
+        if let Some(command) = command {
+            respond(&full_resolution_scope, handle_command(command, None));
+        }
+    }) as Box<dyn Fn()>);
+    let run_full_resolution_callback: js_sys::Function = run_full_resolution
+        .as_ref()
+        .unchecked_ref::<js_sys::Function>()
+        .clone();
+    run_full_resolution.forget();
 
-        let input_message = Reflect::get(&msg.data(), &JsValue::from_str("message"))
-            .unwrap()
-            .as_string()
-            .unwrap();
-        let command = match Command::from_str(&input_message) {
-            Ok(command) => command,
-            Err(error) => {
-                info!("{}", &error);
-                scope_clone
-                    .post_message(&JsValue::from_str(&error))
-                    .unwrap();
-                return;
-            }
+    // runs after every message that was already queued has been received, by then the pending
+    // pipeline is the newest one and the timeouts scheduled for the replaced ones are cancelled
+    let pending_scope = scope.clone();
+    let run_pending_pipeline = Closure::wrap(Box::new(move || {
+        let command = PENDING_PIPELINE.lock().unwrap().take();
+        let Some(Command::Pipeline {
+            request_id,
+            steps,
+            selection,
+        }) = command
+        else {
+            return;
         };
 
-        match command {
-            Command::NewImage => {
-                let image_width = Reflect::get(&msg.data(), &JsValue::from_str("new_width"))
-                    .unwrap()
-                    .as_f64()
-                    .unwrap();
-                let image_data = Reflect::get(&msg.data(), &JsValue::from_str("image_data"))
-                    .unwrap()
-                    .dyn_into::<ArrayBuffer>()
-                    .unwrap();
-                let image_data = Uint8ClampedArray::new(&image_data).to_vec();
-                *UNMODIFIED_IMAGE.lock().unwrap() =
-                    RawImage::new(image_data.to_vec(), image_width as u32);
-            }
-            Command::Invert => {
-                let should_invert = Reflect::get(
-                    &msg.data(),
-                    &JsValue::from_str(&Command::Invert.to_string()),
-                )
-                .unwrap()
-                .dyn_into::<Boolean>()
-                .unwrap()
-                .as_bool()
-                .unwrap();
-                let (image, width, worker_message) = {
-                    let image = (*UNMODIFIED_IMAGE.lock().unwrap()).clone();
-                    if image.buffer().is_empty() {
-                        info!("no image selected to perform image processing");
-                        return;
-                    }
-
-                    let width = image.width();
-                    if should_invert {
-                        (
-                            algorithms::invert(image.to_vec(), width),
-                            width,
-                            WorkerResponseMessage::Invert,
-                        )
-                    } else {
-                        (
-                            image.to_vec(),
-                            width,
-                            WorkerResponseMessage::DisplayOriginalImage,
-                        )
-                    }
-                };
-                let image = Uint8ClampedArray::from(image.as_ref());
-                let output_message = Object::new();
-
-                Reflect::set(
-                    &output_message,
-                    &JsValue::from_str("message"),
-                    &JsValue::from_str(worker_message.to_string().as_ref()),
-                )
-                .unwrap();
-                Reflect::set(
-                    &output_message,
-                    &JsValue::from_str("image_data"),
-                    &image.buffer(),
+        let has_proxy = PROXY_IMAGE.lock().unwrap().is_some();
+        respond(
+            &pending_scope,
+            run_pipeline(request_id, steps.clone(), selection, has_proxy),
+        );
+        if has_proxy {
+            *PENDING_FULL_RESOLUTION.lock().unwrap() = Some(Command::Pipeline {
+                request_id,
+                steps,
+                selection,
+            });
+            cancel_full_resolution(&pending_scope);
+            let handle = pending_scope
+                .set_timeout_with_callback_and_timeout_and_arguments_0(
+                    &run_full_resolution_callback,
+                    FULL_RESOLUTION_DELAY_MS,
                 )
                 .unwrap();
-                Reflect::set(
-                    &output_message,
-                    &JsValue::from_str("width"),
-                    &JsValue::from_f64(width as f64),
-                )
-                .unwrap();
-                let array: Array = Array::new();
-                array.push(&image.buffer());
+            *FULL_RESOLUTION_TIMEOUT.lock().unwrap() = Some(handle);
+        }
+    }) as Box<dyn Fn()>);
+    let run_pending_pipeline_callback: js_sys::Function = run_pending_pipeline
+        .as_ref()
+        .unchecked_ref::<js_sys::Function>()
+        .clone();
+    run_pending_pipeline.forget();
+
+    let on_message = Closure::wrap(Box::new(move |msg: MessageEvent| {
+        web_sys::console::log_1(&"Worker received message".into());
 
+        match Command::decode(msg.data()) {
+            Ok((command @ Command::Pipeline { .. }, _)) => {
+                cancel_full_resolution(&scope_clone);
+                *PENDING_FULL_RESOLUTION.lock().unwrap() = None;
+                *PENDING_PIPELINE.lock().unwrap() = Some(command);
                 scope_clone
-                    .post_message_with_transfer(&output_message, &array)
+                    .set_timeout_with_callback_and_timeout_and_arguments_0(
+                        &run_pending_pipeline_callback,
+                        0,
+                    )
                     .unwrap();
             }
-            Command::BoxBlur => {
-                let box_blur_value = Reflect::get(
-                    &msg.data(),
-                    &JsValue::from_str(&Command::BoxBlur.to_string()),
-                )
-                .unwrap()
-                .dyn_into::<Number>()
-                .unwrap()
-                .as_f64()
-                .unwrap();
-                let box_blur_value = box_blur_value as u32;
-                let (image, width) = {
-                    let image = (*UNMODIFIED_IMAGE.lock().unwrap()).clone();
-                    if image.buffer().is_empty() {
-                        info!("no image selected to perform image processing");
-                        return;
-                    }
-                    let width = image.width();
-                    (
-                        algorithms::box_blur(image.to_vec(), width, box_blur_value),
-                        width,
-                    )
-                };
-                let image = Uint8ClampedArray::from(image.as_ref());
-                let output_message = Object::new();
-
-                Reflect::set(
-                    &output_message,
-                    &JsValue::from_str("message"),
-                    &JsValue::from_str(WorkerResponseMessage::BoxBlur.to_string().as_ref()),
-                )
-                .unwrap();
-                Reflect::set(
-                    &output_message,
-                    &JsValue::from_str("image_data"),
-                    &image.buffer(),
-                )
-                .unwrap();
-                Reflect::set(
-                    &output_message,
-                    &JsValue::from_str("width"),
-                    &JsValue::from_f64(width as f64),
-                )
-                .unwrap();
-                let array: Array = Array::new();
-                array.push(&image.buffer());
+            Ok((command, buffer)) => respond(&scope_clone, handle_command(command, buffer)),
+            Err(error) => respond(&scope_clone, Err(WorkerError::from(error))),
+        }
+    }) as Box<dyn Fn(MessageEvent)>);
 
-                scope_clone
-                    .post_message_with_transfer(&output_message, &array)
-                    .unwrap();
+    scope.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget();
+
+    post_message(&scope, WorkerResponseMessage::Initialized, None);
+}
+
+/// stops the scheduled full resolution render from firing, the render for a newer pipeline is
+/// scheduled once its preview is done
+fn cancel_full_resolution(scope: &DedicatedWorkerGlobalScope) {
+    if let Some(handle) = FULL_RESOLUTION_TIMEOUT.lock().unwrap().take() {
+        scope.clear_timeout_with_handle(handle);
+    }
+}
+
+fn respond(
+    scope: &DedicatedWorkerGlobalScope,
+    response: Result<Vec<(WorkerResponseMessage, Option<Vec<u8>>)>, WorkerError>,
+) {
+    match response {
+        Ok(responses) => {
+            for (worker_message, buffer) in responses {
+                post_message(scope, worker_message, buffer.as_deref());
             }
-            Command::Gamma => {
-                let gamma_value =
-                    Reflect::get(&msg.data(), &JsValue::from_str(&Command::Gamma.to_string()))
-                        .unwrap()
-                        .dyn_into::<Number>()
-                        .unwrap()
-                        .as_f64()
-                        .unwrap();
-                let (image, width) = {
-                    let image = (*UNMODIFIED_IMAGE.lock().unwrap()).clone();
-                    if image.buffer().is_empty() {
-                        info!("no image selected to perform image processing");
-                        return;
-                    }
-                    let width = image.width();
-                    (
-                        algorithms::gamma_transform(image.to_vec(), width, gamma_value as f32),
-                        width,
-                    )
-                };
-                let image = Uint8ClampedArray::from(image.as_ref());
-                let output_message = Object::new();
-
-                Reflect::set(
-                    &output_message,
-                    &JsValue::from_str("message"),
-                    &JsValue::from_str(WorkerResponseMessage::Gamma.to_string().as_ref()),
-                )
-                .unwrap();
-                Reflect::set(
-                    &output_message,
-                    &JsValue::from_str("image_data"),
-                    &image.buffer(),
-                )
-                .unwrap();
-                Reflect::set(
-                    &output_message,
-                    &JsValue::from_str("width"),
-                    &JsValue::from_f64(width as f64),
-                )
-                .unwrap();
-                let array: Array = Array::new();
-                array.push(&image.buffer());
+        }
+        Err(error) => {
+            log::error!("{}", error);
+            post_message(scope, WorkerResponseMessage::Error(error), None);
+        }
+    }
+}
 
-                scope_clone
-                    .post_message_with_transfer(&output_message, &array)
-                    .unwrap();
+/// runs the command and returns the responses along with the buffer to send back with each of them
+fn handle_command(
+    command: Command,
+    buffer: Option<Vec<u8>>,
+) -> Result<Vec<(WorkerResponseMessage, Option<Vec<u8>>)>, WorkerError> {
+    match command {
+        Command::NewImage { file_name } => {
+            // the previous image is dropped even if the new one is rejected so the pipeline is
+            // never applied to an image that isn't on screen anymore
+            *UNMODIFIED_IMAGE.lock().unwrap() = RawImage::new(Vec::new(), 0);
+            *PROXY_IMAGE.lock().unwrap() = None;
+            *PENDING_FULL_RESOLUTION.lock().unwrap() = None;
+
+            let file = buffer.ok_or_else(|| {
+                WorkerError::BadParameters("new image is missing its file".to_string())
+            })?;
+            // phones store photos the way the sensor was held and record how to turn them upright
+            let jpeg_metadata = exif::read_jpeg_metadata(&file);
+            let image = apply_orientation(
+                decode_image(&file, &file_name)?,
+                jpeg_metadata.orientation.unwrap_or(1),
+            );
+            let (width, height) = image.dimensions();
+            let metadata = ImageMetadata {
+                width,
+                height,
+                camera: jpeg_metadata.camera,
+                exposure: jpeg_metadata.exposure,
+                iso: jpeg_metadata.iso,
+                date: jpeg_metadata.date,
+                color_profile: jpeg_metadata.color_profile,
+            };
+
+            let image = RawImage::new(image.into_raw(), width);
+            *PROXY_IMAGE.lock().unwrap() = proxy_image(&image);
+            let pixels = image.buffer().to_vec();
+            *UNMODIFIED_IMAGE.lock().unwrap() = image;
+
+            Ok(vec![(
+                WorkerResponseMessage::Loaded { metadata },
+                Some(pixels),
+            )])
+        }
+        Command::Pipeline {
+            request_id,
+            steps,
+            selection,
+        } => run_pipeline(request_id, steps, selection, false),
+        Command::Export {
+            steps,
+            selection,
+            format,
+            file_name,
+        } => {
+            validate_steps(&steps, selection.as_ref())?;
+
+            let image = (*UNMODIFIED_IMAGE.lock().unwrap()).clone();
+            if image.buffer().is_empty() {
+                return Err(WorkerError::NoImageLoaded);
             }
-            Command::SobelEdgeDetector => {
-                let threshold = Reflect::get(
-                    &msg.data(),
-                    &JsValue::from_str(&Command::SobelEdgeDetector.to_string()),
-                )
-                .unwrap()
-                .dyn_into::<Number>()
-                .unwrap()
-                .as_f64()
-                .unwrap();
-                let threshold = threshold as u32;
-                let (image, width) = {
-                    let image = (*UNMODIFIED_IMAGE.lock().unwrap()).clone();
-                    if image.buffer().is_empty() {
-                        return;
-                    }
-                    let width = image.width();
-                    (
-                        algorithms::sobel_edge_detection(image.to_vec(), width, threshold as u8),
-                        width,
-                    )
-                };
-                let image = Uint8ClampedArray::from(image.as_ref());
-                let output_message = Object::new();
-
-                Reflect::set(
-                    &output_message,
-                    &JsValue::from_str("message"),
-                    &JsValue::from_str(
-                        WorkerResponseMessage::SobelEdgeDetector
-                            .to_string()
-                            .as_ref(),
-                    ),
-                )
-                .unwrap();
-                Reflect::set(
-                    &output_message,
-                    &JsValue::from_str("image_data"),
-                    &image.buffer(),
-                )
-                .unwrap();
-                Reflect::set(
-                    &output_message,
-                    &JsValue::from_str("width"),
-                    &JsValue::from_f64(width as f64),
-                )
-                .unwrap();
-                let array: Array = Array::new();
-                array.push(&image.buffer());
 
-                scope_clone
-                    .post_message_with_transfer(&output_message, &array)
-                    .unwrap();
+            let width = image.width();
+            let height = image.height();
+            let image = apply_steps(image, steps, selection).image;
+            let file = encode_image(image, width, height, format)
+                .map_err(|error| WorkerError::EncodeFailure(error.to_string()))?;
+
+            Ok(vec![(
+                WorkerResponseMessage::Exported { file_name, format },
+                Some(file),
+            )])
+        }
+    }
+}
+
+/// renders the pipeline on the proxy image with its sizes scaled down when there is one and the
+/// preview is wanted, otherwise on the full resolution image
+fn run_pipeline(
+    request_id: u32,
+    steps: Vec<Operation>,
+    selection: Option<Selection>,
+    preview: bool,
+) -> Result<Vec<(WorkerResponseMessage, Option<Vec<u8>>)>, WorkerError> {
+    validate_steps(&steps, selection.as_ref())?;
+
+    // the full resolution image is only cloned when it is rendered, there is only a proxy when
+    // an image is loaded
+    let proxy = preview
+        .then(|| (*PROXY_IMAGE.lock().unwrap()).clone())
+        .flatten();
+    let (image, steps, selection) = match proxy {
+        Some(proxy) => {
+            let scale = proxy.width() as f64 / UNMODIFIED_IMAGE.lock().unwrap().width() as f64;
+            let steps = steps.into_iter().map(|step| step.scaled(scale)).collect();
+            let selection =
+                selection.map(|selection| selection.scaled(scale, proxy.width(), proxy.height()));
+            (proxy, steps, selection)
+        }
+        None => {
+            let image = (*UNMODIFIED_IMAGE.lock().unwrap()).clone();
+            if image.buffer().is_empty() {
+                if steps.is_empty() {
+                    return Ok(Vec::new());
+                }
+                return Err(WorkerError::NoImageLoaded);
             }
+            (image, steps, selection)
         }
-    }) as Box<dyn Fn(MessageEvent)>);
+    };
 
-    scope.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
-    on_message.forget();
+    let width = image.width();
+    let height = image.height();
+    let original_histogram = algorithms::histogram(image.buffer());
+    let output = apply_steps(image, steps, selection);
+    let processed_histogram = algorithms::histogram(&output.image);
+
+    let mut responses = vec![
+        (
+            WorkerResponseMessage::Image {
+                request_id,
+                width,
+                height,
+            },
+            Some(output.image),
+        ),
+        (
+            WorkerResponseMessage::Histograms {
+                request_id,
+                original: original_histogram,
+                processed: processed_histogram,
+            },
+            None,
+        ),
+    ];
+    if let Some(filter_spectrum) = output.filter_spectrum {
+        responses.push((
+            WorkerResponseMessage::FilterSpectrum {
+                request_id,
+                width,
+                height,
+            },
+            Some(filter_spectrum),
+        ));
+    }
+    if let Some(selection_outline) = output.selection_outline {
+        responses.push((
+            WorkerResponseMessage::SelectionOutline {
+                request_id,
+                width,
+                height,
+            },
+            Some(selection_outline),
+        ));
+    }
+    if let Some(corners) = output.corners {
+        responses.push((
+            WorkerResponseMessage::Corners {
+                request_id,
+                corners,
+            },
+            None,
+        ));
+    }
+    if !output.otsu_levels.is_empty() {
+        responses.push((
+            WorkerResponseMessage::OtsuLevels {
+                request_id,
+                levels: output.otsu_levels,
+            },
+            None,
+        ));
+    }
+    Ok(responses)
+}
+
+/// decodes any format the image crate supports, 16 bit images are converted to 8 bits and only
+/// the first frame of an animated gif is used
+/// the format is guessed from the bytes and then from the file name since tga files have no
+/// signature
+fn decode_image(file: &[u8], file_name: &str) -> Result<RgbaImage, WorkerError> {
+    let format = image::guess_format(file)
+        .or_else(|_| ImageFormat::from_path(file_name))
+        .map_err(|_| WorkerError::UnsupportedFormat(file_name.to_string()))?;
+    let decode_error = |error: ImageError| match error {
+        ImageError::Unsupported(_) => WorkerError::UnsupportedFormat(file_name.to_string()),
+        error => WorkerError::DecodeFailure(error.to_string()),
+    };
 
-    let output_message = Object::new();
-    Reflect::set(
-        &output_message,
-        &JsValue::from_str("message"),
-        &JsValue::from_str(WorkerResponseMessage::Initialized.to_string().as_ref()),
-    )
-    .unwrap();
-    scope.post_message(&output_message).unwrap();
+    // the size is checked before decoding so a huge image can't run the worker out of memory
+    let (width, height) = Reader::with_format(Cursor::new(file), format)
+        .into_dimensions()
+        .map_err(decode_error)?;
+    if width as u64 * height as u64 > MAX_IMAGE_PIXELS as u64 {
+        return Err(WorkerError::ImageTooLarge {
+            width,
+            height,
+            max_pixels: MAX_IMAGE_PIXELS,
+        });
+    }
+    if width == 0 || height == 0 {
+        return Err(WorkerError::DecodeFailure("the image is empty".to_string()));
+    }
+
+    let image = Reader::with_format(Cursor::new(file), format)
+        .decode()
+        .map_err(decode_error)?;
+    Ok(image.into_rgba8())
+}
+
+/// turns the image upright for the exif orientation, 1 is already upright, 2 to 4 are mirrored
+/// or upside down and 5 to 8 are on their side
+fn apply_orientation(image: RgbaImage, orientation: u16) -> RgbaImage {
+    match orientation {
+        2 => imageops::flip_horizontal(&image),
+        3 => imageops::rotate180(&image),
+        4 => imageops::flip_vertical(&image),
+        5 => imageops::flip_horizontal(&imageops::rotate90(&image)),
+        6 => imageops::rotate90(&image),
+        7 => imageops::flip_horizontal(&imageops::rotate270(&image)),
+        8 => imageops::rotate270(&image),
+        _ => image,
+    }
+}
+
+/// a downscaled copy of the image so its largest side is at most MAX_PIXEL_LENGTH, none when the
+/// image is already that small
+fn proxy_image(image: &RawImage) -> Option<RawImage> {
+    let (width, height) = (image.width(), image.height());
+    let largest_side = width.max(height);
+    if largest_side <= MAX_PIXEL_LENGTH {
+        return None;
+    }
+
+    let scale = MAX_PIXEL_LENGTH as f64 / largest_side as f64;
+    let proxy_width = ((width as f64 * scale).round() as u32).max(1);
+    let proxy_height = ((height as f64 * scale).round() as u32).max(1);
+    let image = ImageBuffer::<Rgba<u8>, &[u8]>::from_raw(width, height, image.buffer())
+        .expect("image buffer should match its dimensions");
+    let proxy = imageops::resize(&image, proxy_width, proxy_height, FilterType::Triangle);
+
+    Some(RawImage::new(proxy.into_raw(), proxy_width))
+}
+
+fn validate_steps(steps: &[Operation], selection: Option<&Selection>) -> Result<(), WorkerError> {
+    for step in steps.iter() {
+        step.validate().map_err(WorkerError::BadParameters)?;
+    }
+    if let Some(selection) = selection {
+        selection.validate().map_err(WorkerError::BadParameters)?;
+    }
+    Ok(())
+}
+
+/// the processed image along with what the steps produce besides pixels
+struct PipelineOutput {
+    image: Vec<u8>,
+    /// the spectrum preview of the last frequency filter step
+    filter_spectrum: Option<Vec<u8>>,
+    /// the corners found by the last corner detection step
+    corners: Option<Vec<Corner>>,
+    /// the level picked by every otsu threshold step by the step's index
+    otsu_levels: Vec<(u32, u8)>,
+    selection_outline: Option<Vec<u8>>,
 }
 
-// fn resize_image_for_canvas(
-//     image_node: &HtmlImageElement,
-//     canvas: &OffscreenCanvas,
-// ) -> ((f64, f64), (f64, f64)) {
-//     let canvas_offset_width = canvas.width() as f64;
-//     let canvas_offset_height = canvas.width() as f64;
-//     let image_width = image_node.width() as f64;
-//     let image_height = image_node.height() as f64;
-//     let width_scale = canvas.width() as f64 / image_node.width() as f64;
-//     let height_scale = canvas.width() as f64 / image_node.height() as f64;
-//     let scale = if width_scale < height_scale {
-//         width_scale
-//     } else {
-//         height_scale
-//     };
-//     let canvas_width_less_than_image_width = canvas_offset_width < image_width;
-//     let canvas_height_less_than_image_height = canvas_offset_height < image_height;
-//     let (new_width, new_height) =
-//         if canvas_width_less_than_image_width || canvas_height_less_than_image_height {
-//             (
-//                 (image_width * scale).round(),
-//                 (image_height * scale).round(),
-//             )
-//         } else {
-//             (image_width, image_height)
-//         };
-//     let center_x = (canvas_offset_width as f64 - new_width) / 2.0;
-//     let center_y = (canvas_offset_height as f64 - new_height) / 2.0;
-
-//     ((new_width, new_height), (center_x, center_y))
-// }
+/// with a selection every step is mixed back into its input by the selection's mask so only the
+/// selected pixels change
+fn apply_steps(
+    image: RawImage,
+    steps: Vec<Operation>,
+    selection: Option<Selection>,
+) -> PipelineOutput {
+    let width = image.width();
+    // the mask comes from the original image so it doesn't move as steps change the colors
+    let mask = selection.map(|selection| selection.mask(image.buffer(), width));
+    let mut output = PipelineOutput {
+        image: image.to_vec(),
+        filter_spectrum: None,
+        corners: None,
+        otsu_levels: Vec::new(),
+        selection_outline: mask
+            .as_ref()
+            .map(|mask| algorithms::mask_outline(mask, width)),
+    };
+    for (index, step) in steps.into_iter().enumerate() {
+        let input = mask.as_ref().map(|_| output.image.clone());
+        if let Some(level) = step.otsu_level(&output.image) {
+            output.otsu_levels.push((index as u32, level));
+        }
+        match step {
+            Operation::FrequencyFilter(filter) => {
+                let (image, filter_spectrum) =
+                    algorithms::frequency_filter(output.image, width, &filter);
+                output.image = image;
+                output.filter_spectrum = Some(filter_spectrum);
+            }
+            Operation::CornerDetection(detector) => {
+                let mut corners = algorithms::detect_corners(&output.image, width, &detector);
+                if let Some(mask) = &mask {
+                    corners.retain(|corner| mask[(corner.y * width + corner.x) as usize] >= 0.5);
+                }
+                output.corners = Some(corners);
+            }
+            step => output.image = step.apply(output.image, width),
+        }
+        if let (Some(input), Some(mask)) = (input, &mask) {
+            output.image = algorithms::blend_with_mask(&input, output.image, mask);
+        }
+    }
+    output
+}
+
+fn encode_image(
+    image: Vec<u8>,
+    width: u32,
+    height: u32,
+    format: ExportFormat,
+) -> ImageResult<Vec<u8>> {
+    let mut file = Vec::new();
+    match format {
+        ExportFormat::Png => {
+            PngEncoder::new(&mut file).encode(&image, width, height, ColorType::Rgba8)?
+        }
+        ExportFormat::Jpeg { quality } => {
+            // jpeg has no alpha channel so it gets dropped
+            let image: RgbaImage = ImageBuffer::from_raw(width, height, image)
+                .expect("image buffer should match its dimensions");
+            let image: RgbImage = image.convert();
+            JpegEncoder::new_with_quality(&mut file, quality.clamp(1, 100)).encode(
+                &image,
+                width,
+                height,
+                ColorType::Rgb8,
+            )?
+        }
+    }
+
+    Ok(file)
+}
+
+fn post_message(
+    scope: &DedicatedWorkerGlobalScope,
+    worker_message: WorkerResponseMessage,
+    image: Option<&[u8]>,
+) {
+    let (message, transfer) = worker_message.encode(image);
+    scope
+        .post_message_with_transfer(&message, &transfer)
+        .unwrap();
+}
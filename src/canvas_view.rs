@@ -0,0 +1,508 @@
+use std::rc::Rc;
+
+use leptos::{
+    batch, html::Canvas, HtmlElement, NodeRef, SignalGetUntracked, SignalSet, StoredValue,
+};
+use shared::Corner;
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+use crate::app_state::{CompareMode, Comparison, PixelSample, Viewport};
+
+const CORNER_MARKER_COLOR: &str = "#ef4444";
+const CORNER_MARKER_RADIUS: f64 = 4.;
+const DIVIDER_COLOR: &str = "#ffffff";
+const DIVIDER_WIDTH: f64 = 2.;
+/// how far from the divider in canvas pixels a drag still grabs it
+const DIVIDER_GRAB_DISTANCE: f64 = 8.;
+/// the zoom limits as canvas pixels per original image pixel
+const MIN_ZOOM: f64 = 0.05;
+const MAX_ZOOM: f64 = 32.;
+
+/// the canvases the selected image canvas is drawn from
+#[derive(Copy, Clone)]
+pub struct CanvasLayers {
+    /// the processed image at the size the worker rendered it, this is a downscaled preview while
+    /// the parameters are changing
+    pub offscreen: StoredValue<Rc<HtmlCanvasElement>>,
+    /// the full resolution original image
+    pub original: StoredValue<Rc<HtmlCanvasElement>>,
+    /// the border of the selection at the size of the offscreen image, empty without a selection
+    pub selection_outline: StoredValue<Rc<HtmlCanvasElement>>,
+    /// the detected corners in offscreen image coordinates
+    pub corners: StoredValue<Vec<Corner>>,
+}
+
+/// the selected image canvas and everything that decides what is drawn on it and where
+#[derive(Copy, Clone)]
+pub struct CanvasView {
+    pub canvas: NodeRef<Canvas>,
+    pub layers: CanvasLayers,
+    pub comparison: Comparison,
+    pub viewport: Viewport,
+}
+
+/// a rectangle on the canvas in canvas pixels
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Rect {
+    /// the image scaled down to fit the area and centered in it
+    fn fit(image_width: u32, image_height: u32, area: Rect) -> Rect {
+        let (width, height) = scaled_image_size(image_width, image_height, area.width, area.height);
+        Rect {
+            x: area.x + (area.width - width) / 2.,
+            y: area.y + (area.height - height) / 2.,
+            width,
+            height,
+        }
+    }
+
+    /// the image scaled by the zoom with the center point of the image in the middle of the area,
+    /// the center is a fraction of the image's size
+    fn zoomed(
+        image_width: u32,
+        image_height: u32,
+        zoom: f64,
+        (center_u, center_v): (f64, f64),
+        area: Rect,
+    ) -> Rect {
+        let width = image_width as f64 * zoom;
+        let height = image_height as f64 * zoom;
+        let (area_center_x, area_center_y) = area.center();
+        Rect {
+            x: area_center_x - center_u * width,
+            y: area_center_y - center_v * height,
+            width,
+            height,
+        }
+    }
+
+    fn center(&self) -> (f64, f64) {
+        (self.x + self.width / 2., self.y + self.height / 2.)
+    }
+
+    fn contains(&self, x: f64, y: f64) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    /// the position as a fraction of the rect so it maps onto images of any size, none when the
+    /// position is outside of the rect
+    fn fraction(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        self.contains(x, y)
+            .then(|| ((x - self.x) / self.width, (y - self.y) / self.height))
+    }
+}
+
+/// where an image is drawn and the part of the canvas it is shown in, zoomed images are cut off
+/// at the edge of their area
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Placement {
+    area: Rect,
+    image: Rect,
+}
+
+impl CanvasView {
+    /// where the processed image goes on the canvas and the original too when it gets a place of
+    /// its own, in the side by side mode the canvas is split in half with the original on the left
+    fn placements(&self, canvas: &HtmlCanvasElement) -> (Placement, Option<Placement>) {
+        let original_canvas = self.layers.original.get_value();
+        let canvas_area = Rect {
+            x: 0.,
+            y: 0.,
+            width: canvas.width() as f64,
+            height: canvas.height() as f64,
+        };
+        let (processed_area, original_area) = match self.comparison.mode().get_untracked() {
+            CompareMode::SideBySide => {
+                let left = Rect {
+                    width: canvas_area.width / 2.,
+                    ..canvas_area
+                };
+                let right = Rect {
+                    x: left.width,
+                    ..left
+                };
+                (right, Some(left))
+            }
+            CompareMode::Off | CompareMode::Split => (canvas_area, None),
+        };
+
+        // images are sized by the original since the processed image can be a smaller preview, it
+        // gets stretched to the same place when it's drawn
+        let (width, height) = (original_canvas.width(), original_canvas.height());
+        let zoom = self.viewport.zoom().get_untracked();
+        let center = self.viewport.center().get_untracked();
+        let place = |area: Rect| Placement {
+            area,
+            image: match zoom {
+                Some(zoom) => Rect::zoomed(width, height, zoom, center, area),
+                None => Rect::fit(width, height, area),
+            },
+        };
+        (place(processed_area), original_area.map(place))
+    }
+
+    /// canvas pixels per original image pixel for an image drawn in the rect
+    fn zoom_of(&self, image: Rect) -> f64 {
+        image.width / self.layers.original.get_value().width().max(1) as f64
+    }
+
+    /// sizes the canvas to fill its wrapper so the image is drawn at the size it is shown
+    pub fn fit_to_wrapper(&self) {
+        let Some(canvas) = self.canvas.get_untracked() else {
+            return;
+        };
+        let canvas: HtmlElement<Canvas> = canvas.style("width", "100%").style("height", "100%");
+
+        let new_canvas_width = canvas.client_width();
+        let new_canvas_height = canvas.client_height();
+
+        canvas.set_width(new_canvas_width as u32);
+        // TODO: setting the height directly with using .offset_height() or any other height
+        // functions
+        // doesn't work correctly. However if I place the value into a variable first then it works
+        // no idea how this is happening
+        canvas.set_height(new_canvas_height as u32);
+    }
+
+    /// draws the processed image with its overlays, and the original next to it or left of the
+    /// divider when comparing, holding show original replaces the processed image with the
+    /// original
+    pub fn draw(&self) {
+        let Some(canvas) = self.canvas.get_untracked() else {
+            return;
+        };
+        // todo: maybe think about how to handle an offscreen canvas that is empty
+        // should be a no op
+        // right now the default canvas size for the offscreen canvas
+        //  is 150 x 300 so when resizing this code
+        // will just write empty pixels/white pixels to the visible canvas
+        let offscreen_canvas = self.layers.offscreen.get_value();
+        let original_canvas = self.layers.original.get_value();
+        let mode = self.comparison.mode().get_untracked();
+        let (processed, original) = self.placements(&canvas);
+
+        let displayed_zoom = self.zoom_of(processed.image);
+        if displayed_zoom != self.viewport.displayed_zoom().get_untracked() {
+            self.viewport.displayed_zoom().set(displayed_zoom);
+        }
+        // past 100% every pixel is drawn as a sharp square so single pixels can be inspected
+        let is_smooth = displayed_zoom <= 1.;
+
+        let canvas_context = canvas
+            .get_context("2d")
+            .unwrap()
+            .unwrap()
+            .dyn_into::<CanvasRenderingContext2d>()
+            .unwrap();
+        canvas_context.clear_rect(0., 0., canvas.width() as f64, canvas.height() as f64);
+        canvas_context.set_image_smoothing_enabled(is_smooth);
+
+        if let Some(original) = original {
+            clip_to(&canvas_context, original.area);
+            draw_image_in_rect(&canvas_context, &original_canvas, original.image);
+            canvas_context.restore();
+        }
+
+        clip_to(&canvas_context, processed.area);
+        if self.comparison.showing_original().get_untracked() {
+            draw_image_in_rect(&canvas_context, &original_canvas, processed.image);
+        } else {
+            draw_image_in_rect(&canvas_context, &offscreen_canvas, processed.image);
+            draw_selection_outline(
+                &canvas_context,
+                &offscreen_canvas,
+                &self.layers.selection_outline.get_value(),
+                processed.image,
+            );
+            canvas_context.set_image_smoothing_enabled(is_smooth);
+            self.layers.corners.with_value(|corners| {
+                draw_corners(&canvas_context, &offscreen_canvas, corners, processed.image)
+            });
+            // drawn last so the overlays only show on the processed side
+            if mode == CompareMode::Split {
+                draw_split(
+                    &canvas_context,
+                    &original_canvas,
+                    processed.image,
+                    self.comparison.divider().get_untracked(),
+                );
+            }
+        }
+        canvas_context.restore();
+    }
+
+    /// resizes the canvas to its new size on the page, the zoom and the point of the image in the
+    /// middle stay the same so the view doesn't jump
+    pub fn resize(&self) {
+        let Some(canvas) = self.canvas.get_untracked() else {
+            return;
+        };
+        let new_canvas_width = canvas.client_width();
+        let new_canvas_height = canvas.client_height();
+
+        canvas.set_width(new_canvas_width as u32);
+        // TODO: setting the height directly with using .offset_height() or any other height
+        // functions
+        // doesn't work correctly. However if I place the value into a variable first then it works
+        // no idea how this is happening
+        canvas.set_height(new_canvas_height as u32);
+
+        self.draw();
+    }
+
+    /// zooms in or out by the factor keeping the point of the image under the position in place
+    pub fn zoom_at(&self, canvas_x: f64, canvas_y: f64, factor: f64) {
+        let Some(canvas) = self.canvas.get_untracked() else {
+            return;
+        };
+        let original_canvas = self.layers.original.get_value();
+        if original_canvas.width() == 0 || original_canvas.height() == 0 {
+            return;
+        }
+        // in the side by side mode the zoom happens around the image under the pointer
+        let (processed, original) = self.placements(&canvas);
+        let placement = original
+            .filter(|original| original.area.contains(canvas_x, canvas_y))
+            .unwrap_or(processed);
+
+        let zoom = (self.zoom_of(placement.image) * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let u = (canvas_x - placement.image.x) / placement.image.width;
+        let v = (canvas_y - placement.image.y) / placement.image.height;
+        let (area_center_x, area_center_y) = placement.area.center();
+        let center = (
+            u - (canvas_x - area_center_x) / (original_canvas.width() as f64 * zoom),
+            v - (canvas_y - area_center_y) / (original_canvas.height() as f64 * zoom),
+        );
+
+        batch(|| {
+            self.viewport.zoom().set(Some(zoom));
+            self.viewport.center().set(clamp_center(center));
+        });
+    }
+
+    /// moves the image by the distance in canvas pixels, a fitted image starts being zoomed at the
+    /// size it is shown
+    pub fn pan_by(&self, delta_x: f64, delta_y: f64) {
+        let Some(canvas) = self.canvas.get_untracked() else {
+            return;
+        };
+        let (processed, _) = self.placements(&canvas);
+        let image = processed.image;
+        if image.width == 0. || image.height == 0. {
+            return;
+        }
+        let (center_u, center_v) = self.viewport.center().get_untracked();
+
+        batch(|| {
+            if self.viewport.zoom().get_untracked().is_none() {
+                self.viewport.zoom().set(Some(self.zoom_of(image)));
+            }
+            self.viewport.center().set(clamp_center((
+                center_u - delta_x / image.width,
+                center_v - delta_y / image.height,
+            )));
+        });
+    }
+
+    /// maps a position on the canvas back to the pixel under it in the processed image in the
+    /// offscreen canvas and the same spot in the original image, none when the position is
+    /// outside of the drawn images
+    /// the processed image can be a downscaled preview so the position is in original coordinates
+    pub fn sample_pixel(&self, canvas_x: f64, canvas_y: f64) -> Option<PixelSample> {
+        let canvas = self.canvas.get_untracked()?;
+        let offscreen_canvas = self.layers.offscreen.get_value();
+        let original_canvas = self.layers.original.get_value();
+        let (image_width, image_height) = (offscreen_canvas.width(), offscreen_canvas.height());
+        if image_width == 0 || image_height == 0 || original_canvas.width() == 0 {
+            return None;
+        }
+        // both images in the side by side mode map to the same pixel, positions that are in the
+        // image but cut off by the edge of its area aren't on screen
+        let (processed, original) = self.placements(&canvas);
+        let (u, v) = [Some(processed), original]
+            .into_iter()
+            .flatten()
+            .filter(|placement| placement.area.contains(canvas_x, canvas_y))
+            .find_map(|placement| placement.image.fraction(canvas_x, canvas_y))?;
+
+        let read_pixel = |source: &HtmlCanvasElement| {
+            let x = (u * source.width() as f64).floor();
+            let y = (v * source.height() as f64).floor();
+            let context = source
+                .get_context("2d")
+                .unwrap()
+                .unwrap()
+                .dyn_into::<CanvasRenderingContext2d>()
+                .unwrap();
+            let pixel = context.get_image_data(x, y, 1., 1.).ok()?.data();
+            Some([pixel[0], pixel[1], pixel[2], pixel[3]])
+        };
+
+        Some(PixelSample {
+            x: (u * original_canvas.width() as f64) as u32,
+            y: (v * original_canvas.height() as f64) as u32,
+            original: read_pixel(&original_canvas)?,
+            processed: read_pixel(&offscreen_canvas)?,
+        })
+    }
+
+    /// whether a position on the canvas is close enough to the split divider to drag it
+    pub fn is_on_divider(&self, canvas_x: f64) -> bool {
+        let Some(canvas) = self.canvas.get_untracked() else {
+            return false;
+        };
+        let (processed, _) = self.placements(&canvas);
+        let divider_x =
+            processed.image.x + processed.image.width * self.comparison.divider().get_untracked();
+        (divider_x - canvas_x).abs() <= DIVIDER_GRAB_DISTANCE
+    }
+
+    /// the divider position for a position on the canvas, positions outside of the image stop the
+    /// divider at the image's edge
+    pub fn divider_at(&self, canvas_x: f64) -> Option<f64> {
+        let canvas = self.canvas.get_untracked()?;
+        let (processed, _) = self.placements(&canvas);
+        Some(((canvas_x - processed.image.x) / processed.image.width).clamp(0., 1.))
+    }
+}
+
+/// keeps at least the edge of the image in the middle of the view
+fn clamp_center((center_u, center_v): (f64, f64)) -> (f64, f64) {
+    (center_u.clamp(0., 1.), center_v.clamp(0., 1.))
+}
+
+/// saves the context and limits drawing to the area until it is restored
+fn clip_to(canvas_context: &CanvasRenderingContext2d, area: Rect) {
+    canvas_context.save();
+    canvas_context.begin_path();
+    canvas_context.rect(area.x, area.y, area.width, area.height);
+    canvas_context.clip();
+}
+
+fn draw_image_in_rect(
+    canvas_context: &CanvasRenderingContext2d,
+    image: &HtmlCanvasElement,
+    rect: Rect,
+) {
+    canvas_context
+        .draw_image_with_html_canvas_element_and_dw_and_dh(
+            image,
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height,
+        )
+        .unwrap();
+}
+
+/// draws the part of the original left of the divider over the processed image and a line on the
+/// divider, the divider is a fraction of the image's width
+fn draw_split(
+    canvas_context: &CanvasRenderingContext2d,
+    original_canvas: &HtmlCanvasElement,
+    rect: Rect,
+    divider: f64,
+) {
+    if divider > 0. {
+        canvas_context
+            .draw_image_with_html_canvas_element_and_sw_and_sh_and_dx_and_dy_and_dw_and_dh(
+                original_canvas,
+                0.,
+                0.,
+                original_canvas.width() as f64 * divider,
+                original_canvas.height() as f64,
+                rect.x,
+                rect.y,
+                rect.width * divider,
+                rect.height,
+            )
+            .unwrap();
+    }
+
+    let divider_x = rect.x + rect.width * divider;
+    canvas_context.set_stroke_style_str(DIVIDER_COLOR);
+    canvas_context.set_line_width(DIVIDER_WIDTH);
+    canvas_context.begin_path();
+    canvas_context.move_to(divider_x, rect.y);
+    canvas_context.line_to(divider_x, rect.y + rect.height);
+    canvas_context.stroke();
+}
+
+/// draws a circle around every corner, the corners are in the coordinates of the image in the
+/// offscreen canvas which is drawn scaled into the rect
+fn draw_corners(
+    canvas_context: &CanvasRenderingContext2d,
+    offscreen_canvas: &HtmlCanvasElement,
+    corners: &[Corner],
+    rect: Rect,
+) {
+    if corners.is_empty() || offscreen_canvas.width() == 0 {
+        return;
+    }
+    let scale = rect.width / offscreen_canvas.width() as f64;
+
+    canvas_context.set_stroke_style_str(CORNER_MARKER_COLOR);
+    canvas_context.set_line_width(2.);
+    for corner in corners {
+        let x = rect.x + (corner.x as f64 + 0.5) * scale;
+        let y = rect.y + (corner.y as f64 + 0.5) * scale;
+        canvas_context.begin_path();
+        canvas_context
+            .arc(x, y, CORNER_MARKER_RADIUS, 0., std::f64::consts::TAU)
+            .unwrap();
+        canvas_context.stroke();
+    }
+}
+
+/// draws the selection outline on top of the image, the outline has the same size as the image in
+/// the offscreen canvas so it is scaled the same way without smoothing to keep the edge crisp
+fn draw_selection_outline(
+    canvas_context: &CanvasRenderingContext2d,
+    offscreen_canvas: &HtmlCanvasElement,
+    outline_canvas: &HtmlCanvasElement,
+    rect: Rect,
+) {
+    if outline_canvas.width() == 0 || outline_canvas.width() != offscreen_canvas.width() {
+        return;
+    }
+    canvas_context.set_image_smoothing_enabled(false);
+    draw_image_in_rect(canvas_context, outline_canvas, rect);
+}
+
+/// the size an image is drawn at so it fits in the area, images smaller than the area are never
+/// scaled up
+fn scaled_image_size(
+    image_width: u32,
+    image_height: u32,
+    area_width: f64,
+    area_height: f64,
+) -> (f64, f64) {
+    let image_width = image_width as f64;
+    let image_height = image_height as f64;
+
+    let width_scale = area_width / image_width;
+    let height_scale = area_height / image_height;
+    let scale = if width_scale < height_scale {
+        width_scale
+    } else {
+        height_scale
+    };
+
+    let (new_width, new_height) = if area_width < image_width || area_height < image_height {
+        (
+            (image_width * scale).round(),
+            (image_height * scale).round(),
+        )
+    } else {
+        (image_width, image_height)
+    };
+
+    (new_width, new_height)
+}
@@ -2,14 +2,14 @@ use ev::MouseEvent;
 use leptos::*;
 use leptos::{component, view, IntoView};
 
+use crate::app_state::Pipeline;
 use crate::Algorithm;
 
 #[component]
 pub fn AlgorithmList<F>(
     is_screen_desktop_size: ReadSignal<bool>,
-    set_algorithm: WriteSignal<Option<Algorithm>>,
+    pipeline: Pipeline,
     disabled: Signal<bool>,
-    current_algorithm: ReadSignal<Option<Algorithm>>,
     select_image_onclick: F,
 ) -> impl IntoView
 where
@@ -18,8 +18,19 @@ where
     let algorithms = vec![
         Algorithm::Invert,
         Algorithm::Gamma,
+        Algorithm::Exposure,
         Algorithm::BoxBlur,
         Algorithm::SobelEdgeDetector,
+        Algorithm::CannyEdgeDetector,
+        Algorithm::GaussianBlur,
+        Algorithm::UnsharpMask,
+        Algorithm::HueSaturationLightness,
+        Algorithm::HistogramEqualization,
+        Algorithm::Clahe,
+        Algorithm::Threshold,
+        Algorithm::FrequencySpectrum,
+        Algorithm::FrequencyFilter,
+        Algorithm::CornerDetection,
     ];
 
     let desktop_sidebar = view! {
@@ -49,7 +60,7 @@ where
                                         <li
                                             class="menu-item"
                                             on:click=move |_| {
-                                                set_algorithm.set(Some(algorithm));
+                                                pipeline.add(algorithm);
                                             }
                                         >
 
@@ -67,25 +78,25 @@ where
 
     let mobile_bottombar = view! {
         <div class="">
-            <ul class="flex flex-row h-24 bg-gray-200 w-full" disabled=disabled>
+            <ul class="flex flex-row h-24 bg-gray-200 w-full overflow-x-auto" disabled=disabled>
                 {algorithms
                     .clone()
                     .into_iter()
                     .map(|algorithm| {
-                        let is_selected = move || match current_algorithm.get() {
-                            Some(current_algorithm) => current_algorithm == algorithm,
+                        let is_selected = move || match pipeline.selected_step() {
+                            Some(step) => step.algorithm() == algorithm,
                             None => false,
                         };
                         view! {
                             <li
-                                class="w-48 border p-3 hover:bg-gray-300"
+                                class="w-48 shrink-0 border p-3 hover:bg-gray-300"
                                 class=("bg-gray-500", is_selected)
                             >
                                 <span
                                     class="flex items-center justify-center w-full h-full"
                                     disabled=disabled
                                     on:click=move |_| {
-                                        set_algorithm.set(Some(algorithm));
+                                        pipeline.add(algorithm);
                                     }
                                 >
 
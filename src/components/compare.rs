@@ -0,0 +1,51 @@
+use leptos::*;
+use leptos::{component, view, IntoView};
+
+use crate::app_state::{CompareMode, Comparison};
+
+#[component]
+pub fn CompareControls(comparison: Comparison, disabled: Signal<bool>) -> impl IntoView {
+    let mode = comparison.mode();
+    let showing_original = comparison.showing_original();
+
+    let on_mode_change = move |ev| {
+        mode.set(match event_target_value(&ev).as_str() {
+            "split" => CompareMode::Split,
+            "side-by-side" => CompareMode::SideBySide,
+            _ => CompareMode::Off,
+        })
+    };
+
+    view! {
+        <div class="flex flex-row flex-wrap gap-2 p-2 items-center">
+            <select
+                class="select select-sm w-36"
+                title="Compare the processed image with the original, the split divider can be dragged"
+                disabled=disabled
+                on:change=on_mode_change
+            >
+                <option value="off" selected=move || mode.get() == CompareMode::Off>
+                    "Processed"
+                </option>
+                <option value="split" selected=move || mode.get() == CompareMode::Split>
+                    "Split"
+                </option>
+                <option value="side-by-side" selected=move || mode.get() == CompareMode::SideBySide>
+                    "Side by side"
+                </option>
+            </select>
+            // pointer events so holding works with touch too
+            <button
+                class="btn btn-sm"
+                class=("btn-active", move || showing_original.get())
+                title="Hold to show the original image (hold O)"
+                disabled=disabled
+                on:pointerdown=move |_| showing_original.set(true)
+                on:pointerup=move |_| showing_original.set(false)
+                on:pointerleave=move |_| showing_original.set(false)
+            >
+                "Show original"
+            </button>
+        </div>
+    }
+}
@@ -0,0 +1,22 @@
+use leptos::*;
+use leptos::{component, view, IntoView};
+
+#[component]
+pub fn ErrorMessage(error: RwSignal<Option<String>>) -> impl IntoView {
+    move || {
+        error.get().map(|message| {
+            view! {
+                <div class="alert alert-error flex flex-row justify-between items-center m-2">
+                    <span>{message}</span>
+                    <button
+                        class="btn btn-sm btn-ghost"
+                        title="Dismiss"
+                        on:click=move |_| error.set(None)
+                    >
+                        "✕"
+                    </button>
+                </div>
+            }
+        })
+    }
+}
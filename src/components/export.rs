@@ -0,0 +1,97 @@
+use ev::MouseEvent;
+use leptos::*;
+use leptos::{component, view, IntoView};
+use shared::ExportFormat;
+
+const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+#[component]
+pub fn ExportControls<F>(disabled: Signal<bool>, on_export: F) -> impl IntoView
+where
+    F: Fn(ExportFormat) + 'static,
+{
+    let (is_jpeg, set_is_jpeg) = create_signal(false);
+    let (quality, set_quality) = create_signal(DEFAULT_JPEG_QUALITY);
+
+    let format = move || {
+        if is_jpeg.get() {
+            ExportFormat::Jpeg {
+                quality: quality.get(),
+            }
+        } else {
+            ExportFormat::Png
+        }
+    };
+
+    let quality_slider = move || {
+        is_jpeg.get().then(|| {
+            view! {
+                <label for="jpeg-quality-slider" class="text-sm">
+                    "quality "
+                    {quality}
+                </label>
+                <input
+                    id="jpeg-quality-slider"
+                    class="range range-sm w-32"
+                    type="range"
+                    name="jpeg-quality"
+                    min="1"
+                    max="100"
+                    step="1"
+                    prop:value=move || quality.get().to_string()
+                    on:change=move |ev| {
+                        if let Ok(value) = event_target_value(&ev).parse::<u8>() {
+                            set_quality.set(value);
+                        }
+                    }
+                />
+            }
+        })
+    };
+
+    view! {
+        <div class="flex flex-row flex-wrap gap-2 p-2 items-center">
+            <select
+                class="select select-sm w-24"
+                on:change=move |ev| set_is_jpeg.set(event_target_value(&ev) == "jpeg")
+            >
+                <option value="png" selected=true>
+                    "PNG"
+                </option>
+                <option value="jpeg">"JPEG"</option>
+            </select>
+            {quality_slider}
+            <button
+                class="btn btn-sm btn-primary"
+                disabled=disabled
+                on:click=move |_: MouseEvent| on_export(format())
+            >
+                "Export"
+            </button>
+        </div>
+    }
+}
+
+/// builds the name of the exported file from the selected file's name and the applied steps
+/// e.g. photo.jpg with a gamma and box blur step becomes photo-gamma-box-blur.png
+pub fn export_file_name(
+    source_file_name: &str,
+    step_names: &[String],
+    format: ExportFormat,
+) -> String {
+    let stem = match source_file_name.rsplit_once('.') {
+        Some((stem, _extension)) if !stem.is_empty() => stem,
+        _ if source_file_name.is_empty() => "image",
+        _ => source_file_name,
+    };
+
+    let mut file_name = stem.to_string();
+    for step_name in step_names {
+        file_name.push('-');
+        file_name.push_str(&step_name.replace(' ', "-"));
+    }
+    file_name.push('.');
+    file_name.push_str(format.extension());
+
+    file_name
+}
@@ -0,0 +1,104 @@
+use leptos::*;
+use leptos::{component, view, IntoView};
+use shared::Histogram;
+
+/// the height of the chart in svg units, the width is one unit per value
+const CHART_HEIGHT: f64 = 100.;
+
+#[component]
+pub fn HistogramChart(histograms: RwSignal<Option<(Histogram, Histogram)>>) -> impl IntoView {
+    move || {
+        histograms.get().map(|(original, processed)| {
+            view! {
+                <div class="flex flex-row flex-wrap gap-4 p-2">
+                    <HistogramPlot label="original" histogram=original/>
+                    <HistogramPlot label="processed" histogram=processed/>
+                </div>
+            }
+        })
+    }
+}
+
+#[component]
+fn HistogramPlot(label: &'static str, histogram: Histogram) -> impl IntoView {
+    // every curve shares the scale so the channels can be compared with each other
+    let max_count = [
+        &histogram.red,
+        &histogram.green,
+        &histogram.blue,
+        &histogram.luminance,
+    ]
+    .into_iter()
+    .flatten()
+    .copied()
+    .max()
+    .unwrap_or(0)
+    .max(1);
+
+    view! {
+        <div class="flex flex-col gap-1 text-sm">
+            <span class="menu-title">{label}</span>
+            <svg
+                class="w-64 h-24 border rounded bg-base-200"
+                viewBox=format!("0 0 256 {}", CHART_HEIGHT)
+                preserveAspectRatio="none"
+            >
+                <path
+                    d=area_path(&histogram.luminance, max_count)
+                    fill="#9ca3af"
+                    fill-opacity="0.6"
+                ></path>
+                <path
+                    d=line_path(&histogram.red, max_count)
+                    fill="none"
+                    stroke="#ef4444"
+                    vector-effect="non-scaling-stroke"
+                ></path>
+                <path
+                    d=line_path(&histogram.green, max_count)
+                    fill="none"
+                    stroke="#22c55e"
+                    vector-effect="non-scaling-stroke"
+                ></path>
+                <path
+                    d=line_path(&histogram.blue, max_count)
+                    fill="none"
+                    stroke="#3b82f6"
+                    vector-effect="non-scaling-stroke"
+                ></path>
+            </svg>
+        </div>
+    }
+}
+
+/// a line through the top of every bin, each bin is one unit wide
+fn line_path(bins: &[u32], max_count: u32) -> String {
+    bins.iter()
+        .enumerate()
+        .map(|(value, count)| {
+            let command = if value == 0 { 'M' } else { 'L' };
+            format!(
+                "{}{} {:.2}",
+                command,
+                value as f64 + 0.5,
+                bar_top(*count, max_count)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// the bins filled down to the bottom of the chart
+fn area_path(bins: &[u32], max_count: u32) -> String {
+    format!(
+        "M0 {} {} L{} {} Z",
+        CHART_HEIGHT,
+        line_path(bins, max_count).replacen('M', "L", 1),
+        bins.len(),
+        CHART_HEIGHT
+    )
+}
+
+fn bar_top(count: u32, max_count: u32) -> f64 {
+    CHART_HEIGHT - count as f64 / max_count as f64 * CHART_HEIGHT
+}
@@ -0,0 +1,28 @@
+use leptos::*;
+use leptos::{component, view, IntoView};
+
+use crate::app_state::{History, Pipeline};
+
+#[component]
+pub fn HistoryControls(history: History, pipeline: Pipeline) -> impl IntoView {
+    view! {
+        <div class="flex flex-row gap-2 p-2">
+            <button
+                class="btn btn-sm"
+                title="Undo (Ctrl+Z)"
+                disabled=move || !history.can_undo()
+                on:click=move |_| history.undo(pipeline)
+            >
+                "Undo"
+            </button>
+            <button
+                class="btn btn-sm"
+                title="Redo (Ctrl+Shift+Z)"
+                disabled=move || !history.can_redo()
+                on:click=move |_| history.redo(pipeline)
+            >
+                "Redo"
+            </button>
+        </div>
+    }
+}
@@ -0,0 +1,43 @@
+use leptos::*;
+use leptos::{component, view, IntoView};
+use shared::ImageMetadata;
+
+#[component]
+pub fn ImageMetadataPanel(metadata: RwSignal<Option<ImageMetadata>>) -> impl IntoView {
+    move || {
+        metadata.get().map(|metadata| {
+            // only the size is known for every image, the rest is left out when it's missing
+            let rows = [
+                (
+                    "dimensions",
+                    Some(format!("{} × {}", metadata.width, metadata.height)),
+                ),
+                ("camera", metadata.camera),
+                ("exposure", metadata.exposure),
+                ("iso", metadata.iso.map(|iso| iso.to_string())),
+                ("date", metadata.date),
+                ("color profile", metadata.color_profile),
+            ];
+
+            view! {
+                <div class="flex flex-col gap-1 p-2 w-full max-w-xl text-sm">
+                    <span class="menu-title">"Image"</span>
+                    {rows
+                        .into_iter()
+                        .filter_map(|(name, value)| {
+                            value
+                                .map(|value| {
+                                    view! {
+                                        <div class="flex flex-row gap-2">
+                                            <span class="w-28 text-gray-500">{name}</span>
+                                            <span>{value}</span>
+                                        </div>
+                                    }
+                                })
+                        })
+                        .collect_view()}
+                </div>
+            }
+        })
+    }
+}
@@ -1,2 +1,12 @@
 pub mod algorithm_selection;
+pub mod compare;
+pub mod error_message;
+pub mod export;
+pub mod histogram;
+pub mod history;
+pub mod image_metadata;
 pub mod navbar;
+pub mod pipeline;
+pub mod pixel_inspector;
+pub mod selection;
+pub mod zoom;
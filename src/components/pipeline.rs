@@ -0,0 +1,83 @@
+use leptos::*;
+use leptos::{component, view, IntoView};
+
+use crate::app_state::Pipeline;
+
+#[component]
+pub fn PipelineSteps(pipeline: Pipeline) -> impl IntoView {
+    let steps = pipeline.steps();
+    let selected = pipeline.selected();
+
+    let empty_pipeline = move || {
+        steps.with(Vec::is_empty).then(|| {
+            view! { <span class="text-sm text-gray-500">"Select an algorithm to add a step"</span> }
+        })
+    };
+
+    view! {
+        <div class="flex flex-col w-full max-w-xl p-2">
+            <span class="menu-title">"Pipeline"</span>
+            {empty_pipeline}
+            <ol class="flex flex-col gap-1">
+                <For
+                    each=move || steps.get()
+                    key=|step| step.id()
+                    children=move |step| {
+                        let id = step.id();
+                        let enabled = step.enabled();
+                        let is_selected = move || selected.get() == Some(id);
+                        let position = move || {
+                            steps
+                                .with(|steps| {
+                                    steps.iter().position(|step| step.id() == id).unwrap_or_default()
+                                }) + 1
+                        };
+                        view! {
+                            <li
+                                class="flex flex-row items-center gap-2 rounded-md p-1"
+                                class=("bg-gray-200", is_selected)
+                            >
+                                <input
+                                    type="checkbox"
+                                    class="checkbox"
+                                    title="Enable step"
+                                    prop:checked=move || enabled.get()
+                                    on:change=move |_| enabled.update(|enabled| *enabled = !*enabled)
+                                />
+                                <span
+                                    class="grow cursor-pointer"
+                                    on:click=move |_| selected.set(Some(id))
+                                >
+                                    {position}
+                                    ". "
+                                    {step.algorithm().to_string()}
+                                </span>
+                                <button
+                                    class="btn btn-sm"
+                                    title="Move step up"
+                                    on:click=move |_| pipeline.move_up(id)
+                                >
+                                    "↑"
+                                </button>
+                                <button
+                                    class="btn btn-sm"
+                                    title="Move step down"
+                                    on:click=move |_| pipeline.move_down(id)
+                                >
+                                    "↓"
+                                </button>
+                                <button
+                                    class="btn btn-sm btn-error"
+                                    title="Remove step"
+                                    on:click=move |_| pipeline.remove(id)
+                                >
+                                    "✕"
+                                </button>
+                            </li>
+                        }
+                    }
+                />
+            </ol>
+        </div>
+    }
+}
@@ -0,0 +1,109 @@
+use leptos::*;
+use leptos::{component, view, IntoView};
+use shared::algorithms::{rgb_to_hsv, rgb_to_lab};
+
+use crate::app_state::PixelSample;
+
+#[component]
+pub fn PixelInspector(
+    sample: RwSignal<Option<PixelSample>>,
+    pinned: RwSignal<bool>,
+) -> impl IntoView {
+    move || {
+        sample.get().map(|sample| {
+            view! {
+                <div class="flex flex-col gap-1 p-2 w-full max-w-xl text-sm">
+                    <div class="flex flex-row justify-between items-center">
+                        <span class="menu-title">
+                            "Pixel "
+                            {sample.x}
+                            ", "
+                            {sample.y}
+                        </span>
+                        <button
+                            class="btn btn-sm btn-ghost"
+                            title="Pinned pixels stay selected when the pointer moves, click the image to pin or unpin"
+                            on:click=move |_| pinned.update(|pinned| *pinned = !*pinned)
+                        >
+                            {move || if pinned.get() { "Unpin" } else { "Pin" }}
+                        </button>
+                    </div>
+                    <div class="flex flex-row flex-wrap gap-4">
+                        <PixelColor label="original" color=sample.original/>
+                        <PixelColor label="processed" color=sample.processed/>
+                    </div>
+                </div>
+            }
+        })
+    }
+}
+
+#[component]
+fn PixelColor(label: &'static str, color: [u8; 4]) -> impl IntoView {
+    let [red, green, blue, alpha] = color;
+    let (hue, saturation, value) = rgb_to_hsv(red, green, blue);
+    let (lightness, a, b) = rgb_to_lab(red, green, blue);
+
+    let values = [
+        ("hex", hex(color)),
+        (
+            "rgba",
+            format!("rgba({}, {}, {}, {})", red, green, blue, alpha),
+        ),
+        (
+            "hsv",
+            format!(
+                "hsv({:.0}, {:.0}%, {:.0}%)",
+                hue,
+                saturation * 100.,
+                value * 100.
+            ),
+        ),
+        ("lab", format!("lab({:.1} {:.1} {:.1})", lightness, a, b)),
+    ];
+
+    view! {
+        <div class="flex flex-col gap-1">
+            <div class="flex flex-row gap-2 items-center">
+                <span
+                    class="inline-block w-6 h-6 rounded border"
+                    style:background-color=format!("rgba({}, {}, {}, {})", red, green, blue, alpha as f32 / 255.)
+                ></span>
+                <span class="font-semibold">{label}</span>
+            </div>
+            {values
+                .into_iter()
+                .map(|(name, text)| {
+                    let copied_text = text.clone();
+                    view! {
+                        <div class="flex flex-row gap-2 items-center">
+                            <span class="w-10 text-gray-500">{name}</span>
+                            <code class="grow">{text}</code>
+                            <button
+                                class="btn btn-xs"
+                                title="Copy to clipboard"
+                                on:click=move |_| copy_to_clipboard(&copied_text)
+                            >
+                                "Copy"
+                            </button>
+                        </div>
+                    }
+                })
+                .collect_view()}
+        </div>
+    }
+}
+
+/// the color as #rrggbb, the alpha is only added when the pixel isn't opaque
+fn hex([red, green, blue, alpha]: [u8; 4]) -> String {
+    if alpha == u8::MAX {
+        format!("#{:02x}{:02x}{:02x}", red, green, blue)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", red, green, blue, alpha)
+    }
+}
+
+fn copy_to_clipboard(text: &str) {
+    // the returned promise is dropped, a failed copy isn't worth interrupting the user for
+    let _ = window().navigator().clipboard().write_text(text);
+}
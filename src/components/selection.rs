@@ -0,0 +1,103 @@
+use leptos::*;
+use leptos::{component, view, IntoView};
+
+use crate::app_state::{CanvasTool, SelectionState};
+
+#[component]
+pub fn SelectionControls(
+    tool: RwSignal<CanvasTool>,
+    selection: SelectionState,
+    disabled: Signal<bool>,
+) -> impl IntoView {
+    let tolerance = selection.tolerance();
+    let contiguous = selection.contiguous();
+    let feather = selection.feather();
+    let is_magic_wand = move || tool.get() == CanvasTool::MagicWand;
+
+    let toggle_magic_wand = move |_| {
+        tool.update(|tool| {
+            *tool = if *tool == CanvasTool::MagicWand {
+                CanvasTool::Inspect
+            } else {
+                CanvasTool::MagicWand
+            }
+        })
+    };
+
+    // the settings are only shown while they can be used
+    let settings = move || {
+        (is_magic_wand() || selection.seed().with(Option::is_some)).then(|| {
+            view! {
+                <label for="selection-tolerance-slider" class="text-sm">
+                    "tolerance "
+                    {tolerance}
+                </label>
+                <input
+                    id="selection-tolerance-slider"
+                    class="range range-sm w-32"
+                    type="range"
+                    name="selection-tolerance"
+                    min="0"
+                    max="255"
+                    step="1"
+                    prop:value=move || tolerance.get().to_string()
+                    on:change=move |ev| {
+                        if let Ok(value) = event_target_value(&ev).parse::<u32>() {
+                            tolerance.set(value);
+                        }
+                    }
+                />
+                <label class="flex flex-row gap-1 items-center text-sm">
+                    <input
+                        type="checkbox"
+                        class="checkbox checkbox-sm"
+                        prop:checked=move || contiguous.get()
+                        on:change=move |ev| contiguous.set(event_target_checked(&ev))
+                    />
+                    "contiguous"
+                </label>
+                <label for="selection-feather-slider" class="text-sm">
+                    "feather "
+                    {feather}
+                </label>
+                <input
+                    id="selection-feather-slider"
+                    class="range range-sm w-32"
+                    type="range"
+                    name="selection-feather"
+                    min="0"
+                    max="50"
+                    step="1"
+                    prop:value=move || feather.get().to_string()
+                    on:change=move |ev| {
+                        if let Ok(value) = event_target_value(&ev).parse::<f64>() {
+                            feather.set(value);
+                        }
+                    }
+                />
+                <button
+                    class="btn btn-sm"
+                    disabled=move || selection.seed().with(Option::is_none)
+                    on:click=move |_| selection.clear()
+                >
+                    "Clear selection"
+                </button>
+            }
+        })
+    };
+
+    view! {
+        <div class="flex flex-row flex-wrap gap-2 p-2 items-center">
+            <button
+                class="btn btn-sm"
+                class=("btn-active", is_magic_wand)
+                title="Click the image to select pixels with a similar color, steps only change the selected pixels"
+                disabled=disabled
+                on:click=toggle_magic_wand
+            >
+                "Magic wand"
+            </button>
+            {settings}
+        </div>
+    }
+}
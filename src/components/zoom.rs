@@ -0,0 +1,38 @@
+use leptos::*;
+use leptos::{component, view, IntoView};
+
+use crate::app_state::Viewport;
+
+#[component]
+pub fn ZoomControls(viewport: Viewport, disabled: Signal<bool>) -> impl IntoView {
+    let zoom_percentage = move || format!("{:.0}%", viewport.displayed_zoom().get() * 100.);
+
+    view! {
+        <div class="flex flex-row gap-2 p-2 items-center">
+            <span
+                class="text-sm w-14 text-right"
+                title="Scroll or pinch the image to zoom and drag it to pan"
+            >
+                {zoom_percentage}
+            </span>
+            <button
+                class="btn btn-sm"
+                class=("btn-active", move || viewport.zoom().with(Option::is_none))
+                title="Fit the image in the view"
+                disabled=disabled
+                on:click=move |_| viewport.fit()
+            >
+                "Fit"
+            </button>
+            <button
+                class="btn btn-sm"
+                class=("btn-active", move || viewport.zoom().get() == Some(1.))
+                title="Show every pixel of the image"
+                disabled=disabled
+                on:click=move |_| viewport.actual_size()
+            >
+                "100%"
+            </button>
+        </div>
+    }
+}
@@ -1,79 +1,27 @@
 use std::rc::Rc;
-use std::str::FromStr;
+use std::time::Duration;
 
-use js_sys::{ArrayBuffer, Reflect, Uint8ClampedArray};
-use leptos::{create_signal, html::Canvas, NodeRef, ReadSignal, SignalSet, StoredValue};
+use js_sys::{Array, Uint8Array};
+use leptos::{
+    create_signal, html::Canvas, set_timeout, store_value, NodeRef, ReadSignal, RwSignal, SignalSet,
+};
 use log::info;
-use shared::WorkerResponseMessage;
+use shared::{Histogram, ImageMetadata, WorkerProtocol, WorkerResponseMessage};
 use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
 use web_sys::{
-    window, CanvasRenderingContext2d, Event, HtmlCanvasElement, ImageData, MediaQueryListEvent,
-    MessageEvent, Worker, WorkerOptions, WorkerType,
+    window, Blob, BlobPropertyBag, CanvasRenderingContext2d, ClipboardEvent, Event, File,
+    HtmlAnchorElement, HtmlElement, HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement,
+    ImageData, KeyboardEvent, MediaQueryListEvent, MessageEvent, Url, Worker, WorkerOptions,
+    WorkerType,
 };
 
-pub fn use_resize(
-    offscreen_canvas: StoredValue<Rc<HtmlCanvasElement>>,
-    selected_image_canvas: NodeRef<Canvas>,
-) {
+use crate::app_state::{Comparison, History, Pipeline};
+use crate::canvas_view::CanvasView;
+
+pub fn use_resize(canvas_view: CanvasView) {
     let resize_closure: Closure<dyn FnMut(Event)> = Closure::new(move |_event: Event| {
         log::debug!("resizing");
-        let canvas = selected_image_canvas.get_untracked().unwrap();
-        let offscreen_canvas = offscreen_canvas.get_value();
-
-        // todo: maybe think about how to handle an offscreen canvas that is empty
-        // should be a no op
-        // right now the default canvas size for the offscreen canvas
-        //  is 150 x 300 so when resizing this code
-        // will just write empty pixels/white pixels to the visible canvas
-        let offscreen_ctx = offscreen_canvas
-            .get_context("2d")
-            .unwrap()
-            .unwrap()
-            .dyn_into::<CanvasRenderingContext2d>()
-            .unwrap();
-
-        let image_data = offscreen_ctx
-            .get_image_data(
-                0.,
-                0.,
-                offscreen_canvas.width() as f64,
-                offscreen_canvas.height() as f64,
-            )
-            .unwrap()
-            .dyn_into::<ImageData>()
-            .unwrap();
-
-        let (scaled_width, scaled_height) =
-            get_scaled_image_buffer_for_canvas(&image_data, &selected_image_canvas);
-
-        let new_canvas_width = canvas.client_width();
-        let new_canvas_height = canvas.client_height();
-
-        canvas.set_width(new_canvas_width as u32);
-        // TODO: setting the height directly with using .offset_height() or any other height
-        // functions
-        // doesn't work correctly. However if I place the value into a variable first then it works
-        // no idea how this is happening
-        canvas.set_height(new_canvas_height as u32);
-
-        let center_x = (canvas.width() as f64 - scaled_width) / 2.;
-        let center_y = (canvas.height() as f64 - scaled_height) / 2.;
-
-        let canvas_context = canvas
-            .get_context("2d")
-            .unwrap()
-            .unwrap()
-            .dyn_into::<CanvasRenderingContext2d>()
-            .unwrap();
-        canvas_context
-            .draw_image_with_html_canvas_element_and_dw_and_dh(
-                &offscreen_canvas,
-                center_x,
-                center_y,
-                scaled_width,
-                scaled_height,
-            )
-            .unwrap();
+        canvas_view.resize();
     });
 
     window()
@@ -84,86 +32,223 @@ pub fn use_resize(
     resize_closure.forget();
 }
 pub fn use_worker(
-    selected_image_canvas: NodeRef<Canvas>,
-    offscreen_canvas: StoredValue<Rc<HtmlCanvasElement>>,
+    canvas_view: CanvasView,
+    filter_spectrum_canvas: NodeRef<Canvas>,
+    histograms: RwSignal<Option<(Histogram, Histogram)>>,
+    otsu_levels: RwSignal<Vec<(u32, u8)>>,
+    error: RwSignal<Option<String>>,
+    on_image_loaded: impl Fn(ImageMetadata) + 'static,
 ) -> Rc<Worker> {
+    // the request id of the image on screen, images rendered for older parameters are dropped
+    // and overlays are only drawn on the image they were computed for
+    let rendered_request_id = store_value(0);
+    let is_stale = move |request_id: u32| request_id != rendered_request_id.get_value();
+
     let on_worker_message: Closure<dyn FnMut(MessageEvent)> =
         Closure::new(move |message_event: MessageEvent| {
-            let message = &Reflect::get(&message_event.data(), &JsValue::from_str("message"))
-                .unwrap()
-                .as_string()
-                .unwrap();
-            let worker_message = WorkerResponseMessage::from_str(message).unwrap();
+            let (worker_message, buffer) = match WorkerResponseMessage::decode(message_event.data())
+            {
+                Ok(decoded) => decoded,
+                Err(protocol_error) => {
+                    log::error!("{}", protocol_error);
+                    error.set(Some(protocol_error.to_string()));
+                    return;
+                }
+            };
             match worker_message {
                 WorkerResponseMessage::Initialized => {
-                    info!("worker message: {}", worker_message.to_string());
+                    info!("worker message: {:?}", worker_message);
+                }
+                WorkerResponseMessage::Exported { file_name, format } => {
+                    let Some(file) = buffer else {
+                        log::error!("exported message from the worker is missing its file");
+                        return;
+                    };
+                    if let Err(js_error) = download_file(&file, &file_name, format.mime_type()) {
+                        log::error!("{:?}", js_error);
+                        error.set(Some("Could not download the exported image".to_string()));
+                    }
                 }
-                WorkerResponseMessage::Invert
-                | WorkerResponseMessage::BoxBlur
-                | WorkerResponseMessage::Gamma
-                | WorkerResponseMessage::DisplayOriginalImage
-                | WorkerResponseMessage::SobelEdgeDetector => {
-                    let image_data = {
-                        let image_data = Uint8ClampedArray::new(
-                            &Reflect::get(&message_event.data(), &JsValue::from_str("image_data"))
-                                .unwrap()
-                                .dyn_into::<ArrayBuffer>()
-                                .unwrap(),
+                WorkerResponseMessage::FilterSpectrum {
+                    request_id, width, ..
+                } if !is_stale(request_id) => {
+                    let Some(pixels) = buffer else {
+                        log::error!(
+                            "filter spectrum message from the worker is missing its pixels"
                         );
-                        let width = Reflect::get(&message_event.data(), &JsValue::from_str("width"))
-                            .unwrap()
-                            .as_f64()
-                            .unwrap() as u32;
-
-                        ImageData::new_with_u8_clamped_array(
-                            wasm_bindgen::Clamped(&image_data.to_vec()),
-                            width,
-                        )
+                        return;
+                    };
+                    let Some(canvas) = filter_spectrum_canvas.get_untracked() else {
+                        return;
+                    };
+                    let Ok(image_data) =
+                        ImageData::new_with_u8_clamped_array(wasm_bindgen::Clamped(&pixels), width)
+                    else {
+                        log::error!("could not create the filter spectrum image");
+                        return;
+                    };
+
+                    // the canvas is scaled down with css so it is drawn at the image's size
+                    canvas.set_width(image_data.width());
+                    canvas.set_height(image_data.height());
+                    let canvas_context = canvas
+                        .get_context("2d")
                         .unwrap()
+                        .unwrap()
+                        .dyn_into::<CanvasRenderingContext2d>()
+                        .unwrap();
+                    canvas_context.put_image_data(&image_data, 0., 0.).unwrap();
+                }
+                WorkerResponseMessage::SelectionOutline {
+                    request_id, width, ..
+                } if !is_stale(request_id) => {
+                    let Some(pixels) = buffer else {
+                        log::error!(
+                            "selection outline message from the worker is missing its pixels"
+                        );
+                        return;
+                    };
+                    let Ok(image_data) =
+                        ImageData::new_with_u8_clamped_array(wasm_bindgen::Clamped(&pixels), width)
+                    else {
+                        log::error!("could not create the selection outline image");
+                        return;
                     };
 
-                    let selected_image = selected_image_canvas.get().unwrap();
-                    let (scaled_width, scaled_height) =
-                        get_scaled_image_buffer_for_canvas(&image_data, &selected_image_canvas);
+                    let outline_canvas = canvas_view.layers.selection_outline.get_value();
+                    outline_canvas.set_width(image_data.width());
+                    outline_canvas.set_height(image_data.height());
+                    let outline_context = outline_canvas
+                        .get_context("2d")
+                        .unwrap()
+                        .unwrap()
+                        .dyn_into::<CanvasRenderingContext2d>()
+                        .unwrap();
+                    outline_context.put_image_data(&image_data, 0., 0.).unwrap();
 
-                    let canvas_context = selected_image
+                    canvas_view.draw();
+                }
+                WorkerResponseMessage::Corners {
+                    request_id,
+                    corners: new_corners,
+                } if !is_stale(request_id) => {
+                    // the image message comes first so the markers go on top of the new image
+                    canvas_view.layers.corners.set_value(new_corners);
+                    canvas_view.draw();
+                }
+                WorkerResponseMessage::Histograms {
+                    request_id,
+                    original,
+                    processed,
+                } if !is_stale(request_id) => {
+                    histograms.set(Some((original, processed)));
+                }
+                WorkerResponseMessage::OtsuLevels { request_id, levels }
+                    if !is_stale(request_id) =>
+                {
+                    otsu_levels.set(levels);
+                }
+                WorkerResponseMessage::Error(worker_error) => {
+                    log::error!("worker error: {}", worker_error);
+                    error.set(Some(worker_error.to_string()));
+                }
+                WorkerResponseMessage::FilterSpectrum { .. }
+                | WorkerResponseMessage::SelectionOutline { .. }
+                | WorkerResponseMessage::Histograms { .. }
+                | WorkerResponseMessage::OtsuLevels { .. }
+                | WorkerResponseMessage::Corners { .. } => {
+                    log::debug!("dropping an overlay for an image that is no longer shown");
+                }
+                WorkerResponseMessage::Loaded { metadata } => {
+                    let Some(image_data) = buffer else {
+                        log::error!("loaded message from the worker is missing its pixels");
+                        return;
+                    };
+                    let image_data = match ImageData::new_with_u8_clamped_array(
+                        wasm_bindgen::Clamped(&image_data),
+                        metadata.width,
+                    ) {
+                        Ok(image_data) => image_data,
+                        Err(js_error) => {
+                            log::error!("{:?}", js_error);
+                            error.set(Some(
+                                "The worker sent back an image that could not be displayed"
+                                    .to_string(),
+                            ));
+                            return;
+                        }
+                    };
+
+                    let original_canvas = canvas_view.layers.original.get_value();
+                    original_canvas.set_width(image_data.width());
+                    original_canvas.set_height(image_data.height());
+                    original_canvas
                         .get_context("2d")
                         .unwrap()
                         .unwrap()
                         .dyn_into::<CanvasRenderingContext2d>()
+                        .unwrap()
+                        .put_image_data(&image_data, 0., 0.)
                         .unwrap();
 
-                    canvas_context.clear_rect(
-                        0.0,
-                        0.0,
-                        selected_image.width() as f64,
-                        selected_image.height() as f64,
-                    );
+                    canvas_view.fit_to_wrapper();
+                    on_image_loaded(metadata);
+                }
+                WorkerResponseMessage::Image {
+                    request_id, width, ..
+                } => {
+                    if request_id < rendered_request_id.get_value() {
+                        log::debug!(
+                            "dropping image for request {} that arrived late",
+                            request_id
+                        );
+                        return;
+                    }
+                    rendered_request_id.set_value(request_id);
+                    // the levels follow their image when the pipeline still has otsu steps
+                    otsu_levels.set(Vec::new());
+
+                    let Some(image_data) = buffer else {
+                        log::error!("image message from the worker is missing its pixels");
+                        return;
+                    };
+                    let image_data = match ImageData::new_with_u8_clamped_array(
+                        wasm_bindgen::Clamped(&image_data),
+                        width,
+                    ) {
+                        Ok(image_data) => image_data,
+                        Err(js_error) => {
+                            log::error!("{:?}", js_error);
+                            error.set(Some(
+                                "The worker sent back an image that could not be displayed"
+                                    .to_string(),
+                            ));
+                            return;
+                        }
+                    };
 
-                    let center_x = (selected_image.width() as f64 - scaled_width) / 2.;
-                    let center_y = (selected_image.height() as f64 - scaled_height) / 2.;
+                    // overlays only stay on screen while the pipeline keeps sending them, an
+                    // empty outline canvas is skipped when drawing
+                    canvas_view.layers.corners.set_value(Vec::new());
+                    canvas_view
+                        .layers
+                        .selection_outline
+                        .get_value()
+                        .set_width(0);
 
-                    let offscreen_canvas = offscreen_canvas.get_value();
+                    let offscreen_canvas = canvas_view.layers.offscreen.get_value();
                     offscreen_canvas.set_width(image_data.width());
                     offscreen_canvas.set_height(image_data.height());
-
-                    let offscreen_ctx = offscreen_canvas
+                    offscreen_canvas
                         .get_context("2d")
                         .unwrap()
                         .unwrap()
                         .dyn_into::<CanvasRenderingContext2d>()
+                        .unwrap()
+                        .put_image_data(&image_data, 0., 0.)
                         .unwrap();
-                    offscreen_ctx.put_image_data(&image_data, 0., 0.).unwrap();
-
-                    canvas_context
-                        .draw_image_with_html_canvas_element_and_dw_and_dh(
-                            &offscreen_canvas,
-                            center_x,
-                            center_y,
-                            scaled_width,
-                            scaled_height,
-                        )
-                        .unwrap();
+
+                    canvas_view.draw();
                 }
             }
         });
@@ -179,6 +264,40 @@ pub fn use_worker(
     worker.clone()
 }
 
+/// how long a downloaded file's url is kept alive after the download was started
+const REVOKE_DOWNLOAD_URL_DELAY: Duration = Duration::from_secs(10);
+
+fn download_file(file: &[u8], file_name: &str, mime_type: &str) -> Result<(), JsValue> {
+    let parts = Array::new();
+    parts.push(&Uint8Array::from(file));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let anchor = window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .create_element("a")?
+        .dyn_into::<HtmlAnchorElement>()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    // some browsers only start reading the file after the click returns so the url is revoked a
+    // while later instead of right away
+    set_timeout(
+        move || {
+            if let Err(js_error) = Url::revoke_object_url(&url) {
+                log::error!("{:?}", js_error);
+            }
+        },
+        REVOKE_DOWNLOAD_URL_DELAY,
+    );
+    Ok(())
+}
+
 pub fn use_screen_width() -> ReadSignal<bool> {
     let query = "(min-width: 1024px)";
     let media_query = window().unwrap().match_media(query).unwrap().unwrap();
@@ -201,35 +320,113 @@ pub fn use_screen_width() -> ReadSignal<bool> {
     is_screen_desktop_size
 }
 
-fn get_scaled_image_buffer_for_canvas(
-    image_data: &ImageData,
-    canvas: &NodeRef<Canvas>,
-) -> (f64, f64) {
-    let canvas = canvas.get().unwrap();
-    let canvas_client_width = canvas.client_width() as f64;
-    let canvas_client_height = canvas.client_height() as f64;
-    let image_width = image_data.width() as f64;
-    let image_height = image_data.height() as f64;
-    log::debug!("{}", image_width);
-    log::debug!("{}", image_height);
-
-    let width_scale = canvas_client_width / image_width;
-    let height_scale = canvas_client_height / image_height;
-    let scale = if width_scale < height_scale {
-        width_scale
-    } else {
-        height_scale
+pub fn use_history_shortcuts(history: History, pipeline: Pipeline) {
+    let on_keydown: Closure<dyn FnMut(KeyboardEvent)> =
+        Closure::new(move |event: KeyboardEvent| {
+            // meta is for the command key on mac, fields keep their own undo
+            if !(event.ctrl_key() || event.meta_key()) || is_editable_target(&event) {
+                return;
+            }
+            match event.key().to_lowercase().as_str() {
+                "z" if event.shift_key() => {
+                    event.prevent_default();
+                    history.redo(pipeline);
+                }
+                "z" => {
+                    event.prevent_default();
+                    history.undo(pipeline);
+                }
+                "y" => {
+                    event.prevent_default();
+                    history.redo(pipeline);
+                }
+                _ => {}
+            }
+        });
+
+    window()
+        .unwrap()
+        .add_event_listener_with_callback("keydown", on_keydown.as_ref().unchecked_ref())
+        .unwrap();
+
+    on_keydown.forget();
+}
+
+/// whether the event comes from an element that handles keys itself, like an input or a select
+fn is_editable_target(event: &Event) -> bool {
+    let Some(target) = event.target() else {
+        return false;
+    };
+    target.has_type::<HtmlInputElement>()
+        || target.has_type::<HtmlTextAreaElement>()
+        || target.has_type::<HtmlSelectElement>()
+        || target
+            .dyn_ref::<HtmlElement>()
+            .is_some_and(HtmlElement::is_content_editable)
+}
+
+/// shows the original instead of the processed image while the O key is held
+pub fn use_show_original_shortcut(comparison: Comparison) {
+    let showing_original = comparison.showing_original();
+    let is_shortcut = |event: &KeyboardEvent| {
+        event.key().eq_ignore_ascii_case("o")
+            && !(event.ctrl_key() || event.meta_key() || event.alt_key())
     };
 
-    let (new_width, new_height) =
-        if canvas_client_width < image_width || canvas_client_height < image_height {
-            (
-                (image_width * scale).round(),
-                (image_height * scale).round(),
-            )
-        } else {
-            (image_width, image_height)
-        };
-
-    (new_width, new_height)
+    let on_keydown: Closure<dyn FnMut(KeyboardEvent)> =
+        Closure::new(move |event: KeyboardEvent| {
+            // holding a key repeats keydown so the signal is only set once, typing an o in a
+            // field doesn't count but letting go anywhere still ends the hold
+            if is_shortcut(&event) && !event.repeat() && !is_editable_target(&event) {
+                showing_original.set(true);
+            }
+        });
+    let on_keyup: Closure<dyn FnMut(KeyboardEvent)> = Closure::new(move |event: KeyboardEvent| {
+        if is_shortcut(&event) {
+            showing_original.set(false);
+        }
+    });
+    // the key release is missed when the window loses focus while the key is held
+    let on_blur: Closure<dyn FnMut(Event)> = Closure::new(move |_: Event| {
+        showing_original.set(false);
+    });
+
+    let window = window().unwrap();
+    for (event_name, listener) in [
+        ("keydown", on_keydown.as_ref()),
+        ("keyup", on_keyup.as_ref()),
+        ("blur", on_blur.as_ref()),
+    ] {
+        window
+            .add_event_listener_with_callback(event_name, listener.unchecked_ref())
+            .unwrap();
+    }
+
+    on_keydown.forget();
+    on_keyup.forget();
+    on_blur.forget();
+}
+
+/// loads an image pasted from the clipboard, pastes without an image are left alone
+pub fn use_paste_image(on_file: impl Fn(File) + 'static) {
+    let on_paste: Closure<dyn FnMut(ClipboardEvent)> =
+        Closure::new(move |event: ClipboardEvent| {
+            let Some(files) = event.clipboard_data().and_then(|data| data.files()) else {
+                return;
+            };
+            let image = (0..files.length())
+                .filter_map(|index| files.item(index))
+                .find(|file| file.type_().starts_with("image/"));
+            if let Some(image) = image {
+                event.prevent_default();
+                on_file(image);
+            }
+        });
+
+    window()
+        .unwrap()
+        .add_event_listener_with_callback("paste", on_paste.as_ref().unchecked_ref())
+        .unwrap();
+
+    on_paste.forget();
 }
@@ -1,27 +1,49 @@
 mod app_state;
+mod canvas_view;
 mod components;
 mod effects;
 mod event_handlers;
 mod views;
 use std::rc::Rc;
 
-use app_state::{Algorithm, AlgorithmInputState};
+use app_state::{
+    Algorithm, CanvasTool, CompareMode, Comparison, History, Pipeline, SelectionState, Viewport,
+};
 use components::algorithm_selection::AlgorithmList;
+use components::compare::CompareControls;
+use components::error_message::ErrorMessage;
+use components::export::{export_file_name, ExportControls};
+use components::histogram::HistogramChart;
+use components::history::HistoryControls;
+use components::image_metadata::ImageMetadataPanel;
 use components::navbar::NavBar;
-
-use effects::{use_resize, use_screen_width};
-use js_sys::Array;
-use leptos::html::{Canvas, Img, Input};
+use components::pipeline::PipelineSteps;
+use components::pixel_inspector::PixelInspector;
+use components::selection::SelectionControls;
+use components::zoom::ZoomControls;
+
+use canvas_view::{CanvasLayers, CanvasView};
+use effects::{
+    use_history_shortcuts, use_paste_image, use_resize, use_screen_width,
+    use_show_original_shortcut,
+};
+use js_sys::Uint8Array;
+use leptos::html::{Canvas, Input};
 use leptos::wasm_bindgen::JsCast;
 use leptos::*;
 use leptos::{component, create_signal, view, IntoView};
-use log::{debug, info};
-use shared::{
-    BoxBlurMessage, Command, GammaMessage, InvertMessage, NewImageMessage,
-    SobelEdgeDetectionMessage, ToJsObject,
-};
+use log::info;
+use shared::{Command, ExportFormat, WorkerProtocol};
 use views::{CurrentAlgorithm, InvisibleSelectFile};
-use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{DragEvent, File, HtmlCanvasElement, MouseEvent, TouchEvent, WheelEvent};
+
+/// how much a pixel of scrolling zooms, a notch of a mouse wheel is usually 100 pixels
+const WHEEL_ZOOM_SPEED: f64 = 0.002;
+/// the pixels in a line for browsers that scroll by lines
+const WHEEL_LINE_HEIGHT: f64 = 33.;
+/// how far the pointer moves in canvas pixels before a click becomes a pan
+const PAN_THRESHOLD: f64 = 4.;
 
 fn main() {
     console_error_panic_hook::set_once();
@@ -40,11 +62,12 @@ fn main() {
 // it
 #[component]
 fn App() -> impl IntoView {
-    let (algorithm, set_algorithm) = create_signal(Option::None);
-    let (image_url, set_image_url) = create_signal("".to_string());
-    let should_algorithm_buttons_be_disabled = Signal::derive(move || image_url.get().is_empty());
-    let image_ref = create_node_ref::<Img>();
+    let (file_name, set_file_name) = create_signal("".to_string());
+    let is_image_loaded = create_rw_signal(false);
+    let image_metadata = create_rw_signal(None);
+    let should_algorithm_buttons_be_disabled = Signal::derive(move || !is_image_loaded.get());
     let selected_image_canvas = create_node_ref::<Canvas>();
+    let filter_spectrum_canvas = create_node_ref::<Canvas>();
     let offscreen_canvas = store_value(Rc::new(
         window()
             .document()
@@ -54,168 +77,355 @@ fn App() -> impl IntoView {
             .dyn_into::<HtmlCanvasElement>()
             .unwrap(),
     ));
-    let algorithm_state = AlgorithmInputState::default();
-    let gamma = algorithm_state.gamma();
-    let invert = algorithm_state.invert();
-    let box_blur_amount = algorithm_state.box_blur_amount();
-    let sobel_edge_detector_threshold = algorithm_state.sobel_edge_detector_threshold();
-
-    use_resize(offscreen_canvas, selected_image_canvas);
-
-    let worker = effects::use_worker(selected_image_canvas, offscreen_canvas);
-    let onload_worker = worker.clone();
-
-    let handle_image_load = move |_ev| {
-        info!("{}", "image loaded");
-        let image_node = image_ref.get().unwrap();
-        let selected_image_canvas = selected_image_canvas.get().unwrap();
-
-        let selected_image_canvas = selected_image_canvas.style("width", "100%");
-        let selected_image_canvas = selected_image_canvas.style("height", "100%");
-
-        let new_canvas_width = selected_image_canvas.offset_width();
-        let new_canvas_height = selected_image_canvas.offset_height();
-        info!(
-            "selected_image_canvas client width: {}",
-            selected_image_canvas.client_width()
-        );
-        info!(
-            "selected_image_canvas client height: {}",
-            selected_image_canvas.client_height()
-        );
-
-        selected_image_canvas.set_width(new_canvas_width as u32);
-        // TODO: setting the height directly with using .offset_height() or any other height
-        // functions
-        // doesn't work correctly. However if I place the value into a variable first then it works
-        // no idea how this is happening
-        selected_image_canvas.set_height(new_canvas_height as u32);
-
-        let (scaled_width, scaled_height) =
-            get_scaled_image_dimensions_to_canvas(&image_node, &selected_image_canvas);
-
-        let new_canvas_width = selected_image_canvas.client_width();
-        let new_canvas_height = selected_image_canvas.client_height();
-
-        selected_image_canvas.set_width(new_canvas_width as u32);
-        // TODO: setting the height directly with using .offset_height() or any other height
-        // functions
-        // doesn't work correctly. However if I place the value into a variable first then it works
-        // no idea how this is happening
-        selected_image_canvas.set_height(new_canvas_height as u32);
-
-        let center_x = (selected_image_canvas.width() as f64 - scaled_width) / 2.;
-        let center_y = (selected_image_canvas.height() as f64 - scaled_height) / 2.;
-        let canvas_context = selected_image_canvas
-            .get_context("2d")
+    // the full resolution original image, kept around so the pixel inspector can compare it with
+    // the processed image
+    let original_canvas = store_value(Rc::new(
+        window()
+            .document()
             .unwrap()
+            .create_element("canvas")
             .unwrap()
-            .dyn_into::<CanvasRenderingContext2d>()
-            .unwrap();
-        canvas_context
-            .draw_image_with_html_image_element_and_dw_and_dh(
-                &image_node,
-                center_x,
-                center_y,
-                scaled_width,
-                scaled_height,
-            )
-            .unwrap();
-
-        // let image_data = canvas_context
-        //     .get_image_data(center_x, center_y, scaled_width, scaled_height)
-        //     .unwrap();
+            .dyn_into::<HtmlCanvasElement>()
+            .unwrap(),
+    ));
+    let pipeline = Pipeline::default();
+    let history = History::default();
+    let corners = store_value(Vec::new());
+    let selection_outline_canvas = store_value(Rc::new(
+        window()
+            .document()
+            .unwrap()
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<HtmlCanvasElement>()
+            .unwrap(),
+    ));
+    let pixel_sample = create_rw_signal(None);
+    let is_pixel_sample_pinned = create_rw_signal(false);
+    let canvas_tool = create_rw_signal(CanvasTool::Inspect);
+    // set while a slider is dragged, every tick is previewed but only the released value goes
+    // into the history
+    let previewing = create_rw_signal(false);
+    let next_request_id = store_value(0);
+    let selection = SelectionState::default();
+    let comparison = Comparison::default();
+    let viewport = Viewport::default();
+    let canvas_view = CanvasView {
+        canvas: selected_image_canvas,
+        layers: CanvasLayers {
+            offscreen: offscreen_canvas,
+            original: original_canvas,
+            selection_outline: selection_outline_canvas,
+            corners,
+        },
+        comparison,
+        viewport,
+    };
 
-        // reset current algorithm to be None for a new image
-        // reset algorithm values
-        // TODO look into making this a function or something
-        set_algorithm.set(None);
-        algorithm_state.reset();
+    use_resize(canvas_view);
+
+    let error = create_rw_signal(None);
+    let histograms = create_rw_signal(None);
+    let otsu_levels = create_rw_signal(Vec::new());
+
+    // start a new pipeline for the new image once the worker has decoded it
+    let on_image_loaded = move |metadata| {
+        batch(|| {
+            image_metadata.set(Some(metadata));
+            histograms.set(None);
+            otsu_levels.set(Vec::new());
+            pipeline.clear();
+            history.clear();
+            error.set(None);
+            pixel_sample.set(None);
+            is_pixel_sample_pinned.set(false);
+            selection.clear();
+            viewport.fit();
+            is_image_loaded.set(true);
+        });
+    };
 
-        // pass image into the web worker
-        {
-            let offscreen_canvas = window()
-                .document()
-                .unwrap()
-                .create_element("canvas")
-                .unwrap()
-                .dyn_into::<HtmlCanvasElement>()
+    let worker = effects::use_worker(
+        canvas_view,
+        filter_spectrum_canvas,
+        histograms,
+        otsu_levels,
+        error,
+        on_image_loaded,
+    );
+    let load_worker = worker.clone();
+    let export_worker = worker.clone();
+
+    // every way of opening an image ends up here, the file is sent to the worker as is and decoded
+    // there
+    let load_image_file = move |file: File| {
+        info!("loading {}", file.name());
+        set_file_name.set(file.name());
+        is_image_loaded.set(false);
+        image_metadata.set(None);
+        let worker = load_worker.clone();
+        spawn_local(async move {
+            let buffer = match JsFuture::from(file.array_buffer()).await {
+                Ok(buffer) => buffer,
+                Err(js_error) => {
+                    log::error!("{:?}", js_error);
+                    error.set(Some(format!("Could not read {}", file.name())));
+                    return;
+                }
+            };
+            let bytes = Uint8Array::new(&buffer).to_vec();
+            let (message, transfer) = Command::NewImage {
+                file_name: file.name(),
+            }
+            .encode(Some(&bytes));
+            worker
+                .post_message_with_transfer(&message, &transfer)
                 .unwrap();
+        });
+    };
 
-            let context = offscreen_canvas
-                .get_context("2d")
-                .unwrap()
-                .unwrap()
-                .dyn_into::<CanvasRenderingContext2d>()
-                .unwrap();
+    use_history_shortcuts(history, pipeline);
+    use_paste_image(load_image_file.clone());
+    use_show_original_shortcut(comparison);
 
-            let image_width = image_node.width() as f64;
-            let image_height = image_node.height() as f64;
+    // the canvas already has everything it needs to change how the images are compared, zoomed
+    // or panned
+    Effect::new(move |_| {
+        comparison.mode().track();
+        comparison.divider().track();
+        comparison.showing_original().track();
+        viewport.zoom().track();
+        viewport.center().track();
+        if is_image_loaded.get_untracked() {
+            canvas_view.draw();
+        }
+    });
 
-            offscreen_canvas.set_width(image_width as u32);
-            offscreen_canvas.set_height(image_height as u32);
+    // highlights the canvas wrapper while a file is dragged over it, entering a child of the
+    // wrapper leaves the wrapper first so the enters and leaves are counted
+    let is_drop_target = create_rw_signal(false);
+    let drag_depth = store_value(0_u32);
+    let on_drag_enter = move |_: DragEvent| {
+        drag_depth.update_value(|depth| *depth += 1);
+        is_drop_target.set(true);
+    };
+    // dragover has to be cancelled for the wrapper to accept the drop
+    let on_drag_over = move |ev: DragEvent| ev.prevent_default();
+    let on_drag_leave = move |_: DragEvent| {
+        drag_depth.update_value(|depth| *depth = depth.saturating_sub(1));
+        if drag_depth.get_value() == 0 {
+            is_drop_target.set(false);
+        }
+    };
+    let drop_image_file = load_image_file.clone();
+    let on_drop = move |ev: DragEvent| {
+        ev.prevent_default();
+        drag_depth.set_value(0);
+        is_drop_target.set(false);
+        let Some(files) = ev.data_transfer().and_then(|data| data.files()) else {
+            return;
+        };
+        // only images are loaded, the same as pasting
+        let image = (0..files.length())
+            .filter_map(|index| files.item(index))
+            .find(|file| file.type_().starts_with("image/"));
+        if let Some(image) = image {
+            drop_image_file(image);
+        }
+    };
 
-            context
-                .draw_image_with_html_image_element_and_dw_and_dh(
-                    &image_node,
-                    0.,
-                    0.,
-                    image_width,
-                    image_height,
-                )
-                .unwrap();
+    Effect::new(move |_| {
+        let snapshot = pipeline.snapshot();
+        if !previewing.get() {
+            history.record(snapshot);
+        }
+    });
 
-            let data = context
-                .get_image_data(
-                    0.,
-                    0.,
-                    image_node.width() as f64,
-                    image_node.height() as f64,
-                )
-                .unwrap();
+    Effect::new(move |_| {
+        let steps = pipeline.operations();
+        let selection = selection.selection();
+        if !is_image_loaded.get() {
+            return;
+        }
+        next_request_id.update_value(|request_id| *request_id += 1);
+        let (message, _) = Command::Pipeline {
+            request_id: next_request_id.get_value(),
+            steps,
+            selection,
+        }
+        .encode(None);
+        worker.post_message(&message).unwrap();
+    });
 
-            let new_image_message = NewImageMessage::new(
-                Command::NewImage.to_string(),
-                data.data(),
-                image_node.width() as f64,
-                image_node.height() as f64,
-            );
-            let array: Array = Array::new();
-            array.push(&new_image_message.js_clamped_uint8_array().buffer());
+    let on_export = move |format: ExportFormat| {
+        let step_names = pipeline
+            .enabled_algorithms()
+            .iter()
+            .map(Algorithm::to_string)
+            .collect::<Vec<_>>();
+        let (message, _) = Command::Export {
+            steps: pipeline.operations(),
+            selection: selection.selection(),
+            format,
+            file_name: export_file_name(&file_name.get_untracked(), &step_names, format),
+        }
+        .encode(None);
+        export_worker.post_message(&message).unwrap();
+    };
 
-            onload_worker
-                .post_message_with_transfer(&new_image_message.to_js_object(), &array)
-                .unwrap();
+    // the worker only sends a filter spectrum while a frequency filter step is enabled
+    let has_frequency_filter = move || {
+        pipeline
+            .enabled_algorithms()
+            .contains(&Algorithm::FrequencyFilter)
+    };
+
+    let sample_pixel_under_pointer = move |ev: &MouseEvent| {
+        if !is_image_loaded.get_untracked() {
+            return None;
+        }
+        canvas_view.sample_pixel(ev.offset_x() as f64, ev.offset_y() as f64)
+    };
+    // the split divider is dragged from anywhere close to it, the drag ends with the click that
+    // follows releasing the mouse
+    let is_on_split_divider = move |ev: &MouseEvent| {
+        comparison.mode().get_untracked() == CompareMode::Split
+            && is_image_loaded.get_untracked()
+            && canvas_view.is_on_divider(ev.offset_x() as f64)
+    };
+    let is_dragging_divider = create_rw_signal(false);
+    let is_hovering_divider = create_rw_signal(false);
+    // dragging anywhere else pans the image once the pointer moved too far for a click, the
+    // click that follows the drag is ignored
+    let pan_from = store_value(None::<(f64, f64)>);
+    let is_panning = create_rw_signal(false);
+    let on_canvas_mouse_down = move |ev: MouseEvent| {
+        if is_on_split_divider(&ev) {
+            ev.prevent_default();
+            is_dragging_divider.set(true);
+        } else if is_image_loaded.get_untracked() {
+            pan_from.set_value(Some((ev.offset_x() as f64, ev.offset_y() as f64)));
+        }
+    };
+    let on_canvas_mouse_move = move |ev: MouseEvent| {
+        let (x, y) = (ev.offset_x() as f64, ev.offset_y() as f64);
+        if is_dragging_divider.get_untracked() {
+            if let Some(divider) = canvas_view.divider_at(x) {
+                comparison.divider().set(divider);
+            }
+            return;
+        }
+        if let Some((from_x, from_y)) = pan_from.get_value() {
+            if is_panning.get_untracked() || (x - from_x).hypot(y - from_y) > PAN_THRESHOLD {
+                is_panning.set(true);
+                canvas_view.pan_by(x - from_x, y - from_y);
+                pan_from.set_value(Some((x, y)));
+                return;
+            }
+        }
+        is_hovering_divider.set(is_on_split_divider(&ev));
+        if !is_pixel_sample_pinned.get_untracked() {
+            pixel_sample.set(sample_pixel_under_pointer(&ev));
+        }
+    };
+    let on_canvas_mouse_up = move |_: MouseEvent| pan_from.set_value(None);
+    let on_canvas_mouse_leave = move |_: MouseEvent| {
+        pan_from.set_value(None);
+        is_panning.set(false);
+        is_dragging_divider.set(false);
+        is_hovering_divider.set(false);
+        if !is_pixel_sample_pinned.get_untracked() {
+            pixel_sample.set(None);
+        }
+    };
+    // clicking pins the pixel so its values can be copied, clicking again goes back to hovering
+    let on_canvas_click = move |ev: MouseEvent| {
+        if is_dragging_divider.get_untracked() {
+            is_dragging_divider.set(false);
+        } else if is_panning.get_untracked() {
+            is_panning.set(false);
+        } else if canvas_tool.get_untracked() == CanvasTool::MagicWand {
+            if let Some(sample) = sample_pixel_under_pointer(&ev) {
+                selection.seed().set(Some((sample.x, sample.y)));
+            }
+        } else if is_pixel_sample_pinned.get_untracked() {
+            is_pixel_sample_pinned.set(false);
+            pixel_sample.set(sample_pixel_under_pointer(&ev));
+        } else {
+            let sample = sample_pixel_under_pointer(&ev);
+            is_pixel_sample_pinned.set(sample.is_some());
+            pixel_sample.set(sample);
         }
     };
 
-    Effect::new(move |_| {
-        let image_node = image_ref.get().unwrap();
-        image_node.set_src(&image_url.get());
-    });
+    let on_canvas_wheel = move |ev: WheelEvent| {
+        if !is_image_loaded.get_untracked() {
+            return;
+        }
+        ev.prevent_default();
+        // firefox scrolls in lines instead of pixels
+        let delta = if ev.delta_mode() == WheelEvent::DOM_DELTA_LINE {
+            ev.delta_y() * WHEEL_LINE_HEIGHT
+        } else {
+            ev.delta_y()
+        };
+        canvas_view.zoom_at(
+            ev.offset_x() as f64,
+            ev.offset_y() as f64,
+            (-delta * WHEEL_ZOOM_SPEED).exp(),
+        );
+    };
 
-    Effect::new(move |_| {
-        if let Some(current_algorithm) = algorithm.get() {
-            let message = match current_algorithm {
-                Algorithm::Gamma => {
-                    GammaMessage::new(Command::Gamma.to_string(), gamma.get()).to_js_object()
-                }
-                Algorithm::Invert => {
-                    InvertMessage::new(Command::Invert.to_string(), invert.get()).to_js_object()
-                }
-                Algorithm::BoxBlur => {
-                    BoxBlurMessage::new(Command::BoxBlur.to_string(), box_blur_amount.get())
-                        .to_js_object()
-                }
-                Algorithm::SobelEdgeDetector => SobelEdgeDetectionMessage::new(
-                    Command::SobelEdgeDetector.to_string(),
-                    sobel_edge_detector_threshold.get(),
+    // one finger pans and two fingers pinch to zoom around the point between them
+    let touch_positions = store_value(Vec::<(f64, f64)>::new());
+    let touch_positions_on_canvas = move |ev: &TouchEvent| {
+        let Some(canvas) = selected_image_canvas.get_untracked() else {
+            return Vec::new();
+        };
+        let bounds = canvas.get_bounding_client_rect();
+        let touches = ev.touches();
+        (0..touches.length())
+            .filter_map(|index| touches.get(index))
+            .map(|touch| {
+                (
+                    touch.client_x() as f64 - bounds.left(),
+                    touch.client_y() as f64 - bounds.top(),
                 )
-                .to_js_object(),
-            };
-            worker.post_message(&message).unwrap();
+            })
+            .collect::<Vec<_>>()
+    };
+    let on_touches_changed =
+        move |ev: TouchEvent| touch_positions.set_value(touch_positions_on_canvas(&ev));
+    let on_touch_move = move |ev: TouchEvent| {
+        if !is_image_loaded.get_untracked() {
+            return;
         }
-    });
+        let positions = touch_positions_on_canvas(&ev);
+        touch_positions.with_value(
+            |previous| match (previous.as_slice(), positions.as_slice()) {
+                ([from], [to]) => canvas_view.pan_by(to.0 - from.0, to.1 - from.1),
+                ([from_a, from_b], [to_a, to_b]) => {
+                    let from_middle = ((from_a.0 + from_b.0) / 2., (from_a.1 + from_b.1) / 2.);
+                    let to_middle = ((to_a.0 + to_b.0) / 2., (to_a.1 + to_b.1) / 2.);
+                    let from_distance = (from_a.0 - from_b.0).hypot(from_a.1 - from_b.1);
+                    let to_distance = (to_a.0 - to_b.0).hypot(to_a.1 - to_b.1);
+                    batch(|| {
+                        canvas_view
+                            .pan_by(to_middle.0 - from_middle.0, to_middle.1 - from_middle.1);
+                        if from_distance > 0. {
+                            canvas_view.zoom_at(
+                                to_middle.0,
+                                to_middle.1,
+                                to_distance / from_distance,
+                            );
+                        }
+                    });
+                }
+                _ => {}
+            },
+        );
+        touch_positions.set_value(positions);
+    };
 
     let file_input_ref = create_node_ref::<Input>();
     let select_image_onclick = move |_event| {
@@ -254,33 +464,97 @@ fn App() -> impl IntoView {
                     class="flex p-3 justify-center items-center"
                     class=("hidden", is_screen_desktop_size)
                 >
-                    <InvisibleSelectFile file_input_ref=file_input_ref set_image_url=set_image_url/>
+                    <InvisibleSelectFile
+                        file_input_ref=file_input_ref
+                        on_file=load_image_file
+                    />
                     {mobile_select_image_button}
                 </div>
-                <img _ref=image_ref src="" style="display: none" on:load=handle_image_load/>
+                <ErrorMessage error=error/>
 
                 <div class="flex flex-col lg:flex-row lg:flex-row-reverse h-full justify-between">
                     <div class="flex flex-col w-full justify-center items-center">
                         // <div class="flex flex-col grow w-full min-h-[70dvh] max-h-[70dvh] justify-center items-center">
                         <div
                             id="canvas-wrapper"
-                            class="flex justify-center items-center w-full h-full grow p-4"
+                            class="flex justify-center items-center w-full h-full grow p-4 outline-2 -outline-offset-8"
+                            class=("outline-dashed", is_drop_target)
+                            class=("outline-primary", is_drop_target)
+                            title="Drop an image here or paste one with Ctrl+V"
+                            on:dragenter=on_drag_enter
+                            on:dragover=on_drag_over
+                            on:dragleave=on_drag_leave
+                            on:drop=on_drop
                         >
-                            <canvas _ref=selected_image_canvas id="selected-image"></canvas>
+                            <canvas
+                                _ref=selected_image_canvas
+                                id="selected-image"
+                                class=move || {
+                                    if is_dragging_divider.get() || is_hovering_divider.get() {
+                                        "touch-none cursor-col-resize"
+                                    } else if is_panning.get() {
+                                        "touch-none cursor-grabbing"
+                                    } else {
+                                        "touch-none cursor-crosshair"
+                                    }
+                                }
+                                on:mousedown=on_canvas_mouse_down
+                                on:mousemove=on_canvas_mouse_move
+                                on:mouseup=on_canvas_mouse_up
+                                on:mouseleave=on_canvas_mouse_leave
+                                on:click=on_canvas_click
+                                // undelegated so the listener isn't passive and can stop the page
+                                // from scrolling
+                                on:wheel:undelegated=on_canvas_wheel
+                                on:touchstart=on_touches_changed
+                                on:touchmove=on_touch_move
+                                on:touchend=on_touches_changed
+                                on:touchcancel=on_touches_changed
+                            ></canvas>
+                        </div>
+                        <div class="flex flex-row flex-wrap justify-center items-center">
+                            <HistoryControls history=history pipeline=pipeline/>
+                            <ZoomControls
+                                viewport=viewport
+                                disabled=should_algorithm_buttons_be_disabled
+                            />
+                            <CompareControls
+                                comparison=comparison
+                                disabled=should_algorithm_buttons_be_disabled
+                            />
+                            <SelectionControls
+                                tool=canvas_tool
+                                selection=selection
+                                disabled=should_algorithm_buttons_be_disabled
+                            />
+                            <ExportControls
+                                disabled=should_algorithm_buttons_be_disabled
+                                on_export=on_export
+                            />
                         </div>
-                        <CurrentAlgorithm
-                            gamma=gamma
-                            invert=invert
-                            box_blur_amount=box_blur_amount
-                            sobel_edge_detector_threshold=sobel_edge_detector_threshold
-                            algorithm=algorithm
-                        />
+                        <ImageMetadataPanel metadata=image_metadata/>
+                        <PixelInspector sample=pixel_sample pinned=is_pixel_sample_pinned/>
+                        <PipelineSteps pipeline=pipeline/>
+                        <div class="flex flex-row flex-wrap justify-center items-start">
+                            <CurrentAlgorithm
+                                pipeline=pipeline
+                                previewing=previewing
+                                otsu_levels=otsu_levels
+                            />
+                            <HistogramChart histograms=histograms/>
+                        </div>
+                        <canvas
+                            _ref=filter_spectrum_canvas
+                            id="filter-spectrum"
+                            class="max-w-64 max-h-64 p-2"
+                            class=("hidden", move || !has_frequency_filter())
+                            title="spectrum with the filter mask, removed frequencies are red"
+                        ></canvas>
                     </div>
                     <AlgorithmList
                         is_screen_desktop_size=is_screen_desktop_size
                         disabled=should_algorithm_buttons_be_disabled
-                        set_algorithm=set_algorithm
-                        current_algorithm=algorithm
+                        pipeline=pipeline
                         select_image_onclick=select_image_onclick
                     />
                 </div>
@@ -288,35 +562,3 @@ fn App() -> impl IntoView {
         </div>
     }
 }
-
-fn get_scaled_image_dimensions_to_canvas(
-    image_node: &HtmlImageElement,
-    canvas: &HtmlCanvasElement,
-) -> (f64, f64) {
-    let canvas_client_width = canvas.client_width() as f64;
-    let canvas_client_height = canvas.client_height() as f64;
-    let image_width = image_node.width() as f64;
-    let image_height = image_node.height() as f64;
-    log::debug!("{}", image_width);
-    log::debug!("{}", image_height);
-
-    let width_scale = canvas_client_width / image_width;
-    let height_scale = canvas_client_height / image_height;
-    let scale = if width_scale < height_scale {
-        width_scale
-    } else {
-        height_scale
-    };
-
-    let (new_width, new_height) =
-        if canvas_client_width < image_width || canvas_client_height < image_height {
-            (
-                (image_width * scale).round(),
-                (image_height * scale).round(),
-            )
-        } else {
-            (image_width, image_height)
-        };
-
-    (new_width, new_height)
-}
@@ -1,18 +1,31 @@
-use std::ops::Not;
-
 use leptos::wasm_bindgen::JsCast;
-use leptos::{component, html::Input, view, IntoView, ReadSignal, RwSignal, SignalSet};
-use leptos::{NodeRef, SignalGet, WriteSignal};
+use leptos::{component, html::Input, view, IntoView, RwSignal, SignalSet};
+use leptos::{event_target_value, NodeRef, Signal, SignalGet, SignalWith, WriteSignal};
 use log::info;
-use web_sys::{Event, HtmlInputElement, MouseEvent, Url};
+use web_sys::{Event, File, HtmlInputElement};
 
-use crate::Algorithm;
+use shared::{CornerMethod, FilterShape, FrequencyBand, ThresholdMethod};
 
-#[component]
-pub fn Gamma(gamma: RwSignal<f64>) -> impl IntoView {
-    let default_gamma = 1.;
-    gamma.set(default_gamma);
+use crate::app_state::{Pipeline, StepParameters};
+
+/// previews the value while the slider is dragged
+fn preview(previewing: RwSignal<bool>, slider: impl Fn(Event)) -> impl Fn(Event) {
+    move |ev| {
+        previewing.set(true);
+        slider(ev);
+    }
+}
+
+/// commits the value once the slider is released so it is recorded in the history
+fn commit(previewing: RwSignal<bool>, slider: impl Fn(Event)) -> impl Fn(Event) {
+    move |ev| {
+        slider(ev);
+        previewing.set(false);
+    }
+}
 
+#[component]
+pub fn Gamma(gamma: RwSignal<f64>, previewing: RwSignal<bool>) -> impl IntoView {
     let slider = move |ev: Event| {
         let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
         let value = element.value();
@@ -33,29 +46,21 @@ pub fn Gamma(gamma: RwSignal<f64>) -> impl IntoView {
             min="0.2"
             max="5"
             step="0.1"
-            value=default_gamma.to_string()
-            on:change=slider
+            prop:value=move || gamma.get().to_string()
+            on:input=preview(previewing, slider)
+            on:change=commit(previewing, slider)
         />
     }
 }
 
+/// inverting has no parameters, the step's toggle in the pipeline turns it on and off
 #[component]
-pub fn Invert(invert: RwSignal<bool>) -> impl IntoView {
-    let click = move |_ev: MouseEvent| {
-        invert.set(invert.get().not());
-    };
-    view! {
-        <button class="btn lg:w-32 sm:w-9/12" on:click=click>
-            "Invert"
-        </button>
-    }
+pub fn Invert() -> impl IntoView {
+    view! { <span class="some-custom-css">"inverts the colors of the image"</span> }
 }
 
 #[component]
-pub fn BoxBlur(box_blur_amount: RwSignal<u32>) -> impl IntoView {
-    let box_blur = 1;
-    box_blur_amount.set(box_blur);
-
+pub fn BoxBlur(box_blur_amount: RwSignal<u32>, previewing: RwSignal<bool>) -> impl IntoView {
     let slider = move |ev: Event| {
         let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
         let value = element.value();
@@ -77,17 +82,15 @@ pub fn BoxBlur(box_blur_amount: RwSignal<u32>) -> impl IntoView {
             min="1"
             max="99"
             step="2"
-            value=box_blur.to_string()
-            on:change=slider
+            prop:value=move || box_blur_amount.get().to_string()
+            on:input=preview(previewing, slider)
+            on:change=commit(previewing, slider)
         />
     }
 }
 
 #[component]
-pub fn SobelEdgeDetector(threshold: RwSignal<u32>) -> impl IntoView {
-    let default_threshold = 128;
-    threshold.set(default_threshold);
-
+pub fn SobelEdgeDetector(threshold: RwSignal<u32>, previewing: RwSignal<bool>) -> impl IntoView {
     let slider = move |ev: Event| {
         let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
         let value = element.value();
@@ -109,126 +112,866 @@ pub fn SobelEdgeDetector(threshold: RwSignal<u32>) -> impl IntoView {
             min="1"
             max="255"
             step="1"
-            value=default_threshold.to_string()
-            on:change=slider
+            prop:value=move || threshold.get().to_string()
+            on:input=preview(previewing, slider)
+            on:change=commit(previewing, slider)
+        />
+    }
+}
+
+#[component]
+pub fn CannyEdgeDetector(
+    sigma: RwSignal<f64>,
+    low_threshold: RwSignal<u32>,
+    high_threshold: RwSignal<u32>,
+    previewing: RwSignal<bool>,
+) -> impl IntoView {
+    let sigma_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        sigma.set(value.parse::<f64>().unwrap());
+        info!("sliding for canny edge detector sigma: {}", sigma.get());
+    };
+    let low_threshold_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        low_threshold.set(value.parse::<u32>().unwrap());
+        info!(
+            "sliding for canny edge detector low threshold: {}",
+            low_threshold.get()
+        );
+    };
+    let high_threshold_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        high_threshold.set(value.parse::<u32>().unwrap());
+        info!(
+            "sliding for canny edge detector high threshold: {}",
+            high_threshold.get()
+        );
+    };
+
+    // each threshold is limited by the other so the low one can never end up above the high one
+    view! {
+        <div class="flex flex-col">
+            <label for="canny-sigma-slider" class="some-custom-css">
+                "sigma "
+                {sigma}
+            </label>
+            <input
+                id="canny-sigma-slider"
+                class="range"
+                type="range"
+                name="canny-sigma"
+                min="0.5"
+                max="5"
+                step="0.1"
+                prop:value=move || sigma.get().to_string()
+                on:input=preview(previewing, sigma_slider)
+                on:change=commit(previewing, sigma_slider)
+            />
+            <label for="canny-low-threshold-slider" class="some-custom-css">
+                "low threshold "
+                {low_threshold}
+            </label>
+            <input
+                id="canny-low-threshold-slider"
+                class="range"
+                type="range"
+                name="canny-low-threshold"
+                min="1"
+                max=move || high_threshold.get().to_string()
+                step="1"
+                prop:value=move || low_threshold.get().to_string()
+                on:input=preview(previewing, low_threshold_slider)
+                on:change=commit(previewing, low_threshold_slider)
+            />
+            <label for="canny-high-threshold-slider" class="some-custom-css">
+                "high threshold "
+                {high_threshold}
+            </label>
+            <input
+                id="canny-high-threshold-slider"
+                class="range"
+                type="range"
+                name="canny-high-threshold"
+                min=move || low_threshold.get().to_string()
+                max="255"
+                step="1"
+                prop:value=move || high_threshold.get().to_string()
+                on:input=preview(previewing, high_threshold_slider)
+                on:change=commit(previewing, high_threshold_slider)
+            />
+        </div>
+    }
+}
+
+#[component]
+pub fn GaussianBlur(sigma: RwSignal<f64>, previewing: RwSignal<bool>) -> impl IntoView {
+    let slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        sigma.set(value.parse::<f64>().unwrap());
+        info!("sliding for gaussian blur: {}", sigma.get());
+    };
+
+    view! {
+        <label for="gaussian-blur-slider" class="some-custom-css">
+            "gaussian blur sigma "
+            {sigma}
+        </label>
+        <input
+            id="gaussian-blur-slider"
+            class="range"
+            type="range"
+            name="gaussian-blur"
+            min="0.5"
+            max="20"
+            step="0.5"
+            prop:value=move || sigma.get().to_string()
+            on:input=preview(previewing, slider)
+            on:change=commit(previewing, slider)
         />
     }
 }
 
-// #[component]
-// pub fn Image() -> impl IntoView {
-//     let image_ref = create_node_ref::<Img>();
-
-//     let handle_image_load = move |ev| {
-//         info!("{}", "image loaded");
-//         let image_node = image_ref.get().unwrap();
-//         let canvas_wrapper_node = canvas_wrapper.get().unwrap();
-//         let selected_image_canvas = selected_image_canvas.get().unwrap();
-
-//         // let canvas_wrapper_width = canvas_wrapper_node.client_width();
-//         // let canvas_wrapper_height = canvas_wrapper_node.client_height();
-//         let selected_image_canvas = selected_image_canvas.style("width", "100%");
-//         let selected_image_canvas = selected_image_canvas.style("height", "100%");
-//         // let new_canvas_width = selected_image_canvas.client_width();
-//         // let new_canvas_height = selected_image_canvas.client_height();
-//         let new_canvas_width = selected_image_canvas.offset_width();
-//         let new_canvas_height = selected_image_canvas.offset_height();
-//         info!(
-//             "selected_image_canvas client width: {}",
-//             selected_image_canvas.client_width()
-//         );
-//         info!(
-//             "selected_image_canvas client height: {}",
-//             selected_image_canvas.client_height()
-//         );
-
-//         selected_image_canvas.set_width(new_canvas_width as u32);
-//         // TODO: setting the height directly with using .offset_height() or any other height
-//         // functions
-//         // doesn't work correctly. However if I place the value into a variable first then it works
-//         // no idea how this is happening
-//         selected_image_canvas.set_height(new_canvas_width as u32);
-
-//         let (scaled_width, scaled_height) =
-//             get_scaled_image_dimensions_to_canvas(&image_node, &selected_image_canvas);
-
-//         let new_canvas_width = selected_image_canvas.client_width();
-//         let new_canvas_height = selected_image_canvas.client_height();
-
-//         selected_image_canvas.set_width(new_canvas_width as u32);
-//         // TODO: setting the height directly with using .offset_height() or any other height
-//         // functions
-//         // doesn't work correctly. However if I place the value into a variable first then it works
-//         // no idea how this is happening
-//         selected_image_canvas.set_height(new_canvas_height as u32);
-
-//         let center_x = (selected_image_canvas.width() as f64 - scaled_width) / 2.;
-//         let center_y = (selected_image_canvas.height() as f64 - scaled_height) / 2.;
-//         let canvas_context = selected_image_canvas
-//             .get_context("2d")
-//             .unwrap()
-//             .unwrap()
-//             .dyn_into::<CanvasRenderingContext2d>()
-//             .unwrap();
-//         canvas_context
-//             .draw_image_with_html_image_element_and_dw_and_dh(
-//                 &image_node,
-//                 center_x,
-//                 center_y,
-//                 scaled_width,
-//                 scaled_height,
-//             )
-//             .unwrap();
-
-//         let image_data = canvas_context
-//             .get_image_data(center_x, center_y, scaled_width, scaled_height)
-//             .unwrap();
-
-//         // reset current algorithm to be None for a new image
-//         // reset algorithm values
-//         // TODO look into making this a function or something
-//         set_algorithm(None);
-//         invert.set(false);
-//         box_blur_amount.set(1);
-//         gamma.set(1.);
-
-//         let new_image_message = NewImageMessage::new(
-//             Command::NewImage.to_string(),
-//             image_data.data(),
-//             center_x,
-//             center_y,
-//             scaled_width,
-//             scaled_height,
-//         );
-//         let mut array: Array = Array::new();
-//         array.push(&new_image_message.js_clamped_uint8_array().buffer());
-
-//         onload_worker
-//             .post_message_with_transfer(&new_image_message.to_js_object(), &array)
-//             .unwrap();
-//     };
-
-//     view! { <img _ref=image_ref src="" style="display: none" on:load=handle_image_load/> }
-// }
+#[component]
+pub fn UnsharpMask(
+    amount: RwSignal<f64>,
+    radius: RwSignal<f64>,
+    threshold: RwSignal<u32>,
+    previewing: RwSignal<bool>,
+) -> impl IntoView {
+    let amount_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        amount.set(value.parse::<f64>().unwrap());
+        info!("sliding for unsharp mask amount: {}", amount.get());
+    };
+    let radius_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        radius.set(value.parse::<f64>().unwrap());
+        info!("sliding for unsharp mask radius: {}", radius.get());
+    };
+    let threshold_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        let value = value.parse::<f64>().unwrap() as u32;
+        threshold.set(value);
+        info!("sliding for unsharp mask threshold: {}", threshold.get());
+    };
+
+    view! {
+        <div class="flex flex-col">
+            <label for="unsharp-mask-amount-slider" class="some-custom-css">
+                "amount "
+                {amount}
+            </label>
+            <input
+                id="unsharp-mask-amount-slider"
+                class="range"
+                type="range"
+                name="unsharp-mask-amount"
+                min="0"
+                max="5"
+                step="0.1"
+                prop:value=move || amount.get().to_string()
+                on:input=preview(previewing, amount_slider)
+                on:change=commit(previewing, amount_slider)
+            />
+            <label for="unsharp-mask-radius-slider" class="some-custom-css">
+                "radius "
+                {radius}
+            </label>
+            <input
+                id="unsharp-mask-radius-slider"
+                class="range"
+                type="range"
+                name="unsharp-mask-radius"
+                min="0.5"
+                max="10"
+                step="0.5"
+                prop:value=move || radius.get().to_string()
+                on:input=preview(previewing, radius_slider)
+                on:change=commit(previewing, radius_slider)
+            />
+            <label for="unsharp-mask-threshold-slider" class="some-custom-css">
+                "threshold "
+                {threshold}
+            </label>
+            <input
+                id="unsharp-mask-threshold-slider"
+                class="range"
+                type="range"
+                name="unsharp-mask-threshold"
+                min="0"
+                max="255"
+                step="1"
+                prop:value=move || threshold.get().to_string()
+                on:input=preview(previewing, threshold_slider)
+                on:change=commit(previewing, threshold_slider)
+            />
+        </div>
+    }
+}
+
+#[component]
+pub fn HueSaturationLightness(
+    hue: RwSignal<f64>,
+    saturation: RwSignal<f64>,
+    lightness: RwSignal<f64>,
+    previewing: RwSignal<bool>,
+) -> impl IntoView {
+    let hue_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        hue.set(value.parse::<f64>().unwrap());
+        info!("sliding for hue: {}", hue.get());
+    };
+    let saturation_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        saturation.set(value.parse::<f64>().unwrap());
+        info!("sliding for saturation: {}", saturation.get());
+    };
+    let lightness_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        lightness.set(value.parse::<f64>().unwrap());
+        info!("sliding for lightness: {}", lightness.get());
+    };
+
+    view! {
+        <div class="flex flex-col">
+            <label for="hue-slider" class="some-custom-css">
+                "hue "
+                {hue}
+            </label>
+            <input
+                id="hue-slider"
+                class="range"
+                type="range"
+                name="hue"
+                min="-180"
+                max="180"
+                step="1"
+                prop:value=move || hue.get().to_string()
+                on:input=preview(previewing, hue_slider)
+                on:change=commit(previewing, hue_slider)
+            />
+            <label for="saturation-slider" class="some-custom-css">
+                "saturation "
+                {saturation}
+            </label>
+            <input
+                id="saturation-slider"
+                class="range"
+                type="range"
+                name="saturation"
+                min="-100"
+                max="100"
+                step="1"
+                prop:value=move || saturation.get().to_string()
+                on:input=preview(previewing, saturation_slider)
+                on:change=commit(previewing, saturation_slider)
+            />
+            <label for="lightness-slider" class="some-custom-css">
+                "lightness "
+                {lightness}
+            </label>
+            <input
+                id="lightness-slider"
+                class="range"
+                type="range"
+                name="lightness"
+                min="-100"
+                max="100"
+                step="1"
+                prop:value=move || lightness.get().to_string()
+                on:input=preview(previewing, lightness_slider)
+                on:change=commit(previewing, lightness_slider)
+            />
+        </div>
+    }
+}
 
 #[component]
-pub fn InvisibleSelectFile(
-    file_input_ref: NodeRef<Input>,
-    set_image_url: WriteSignal<String>,
+pub fn Exposure(
+    stops: RwSignal<f64>,
+    highlight_rolloff: RwSignal<bool>,
+    previewing: RwSignal<bool>,
 ) -> impl IntoView {
+    let slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        stops.set(value.parse::<f64>().unwrap());
+        info!("sliding for exposure: {}", stops.get());
+    };
+    let toggle_rolloff = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        highlight_rolloff.set(element.checked());
+    };
+
+    view! {
+        <div class="flex flex-col">
+            <label for="exposure-slider" class="some-custom-css">
+                "exposure "
+                {stops}
+                " EV"
+            </label>
+            <input
+                id="exposure-slider"
+                class="range"
+                type="range"
+                name="exposure"
+                min="-3"
+                max="3"
+                step="0.1"
+                prop:value=move || stops.get().to_string()
+                on:input=preview(previewing, slider)
+                on:change=commit(previewing, slider)
+            />
+            <label class="flex flex-row gap-2 items-center">
+                <input
+                    type="checkbox"
+                    class="checkbox"
+                    prop:checked=move || highlight_rolloff.get()
+                    on:change=toggle_rolloff
+                />
+                "highlight roll-off"
+            </label>
+        </div>
+    }
+}
+
+/// there is nothing to adjust, the step is turned off in the pipeline like any other
+#[component]
+pub fn HistogramEqualization() -> impl IntoView {
+    view! {
+        <span class="some-custom-css">
+            "equalizes the luminance of the whole image, clahe evens out contrast locally instead"
+        </span>
+    }
+}
+
+#[component]
+pub fn Clahe(
+    tile_grid_size: RwSignal<u32>,
+    clip_limit: RwSignal<f64>,
+    previewing: RwSignal<bool>,
+) -> impl IntoView {
+    let tile_grid_size_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        tile_grid_size.set(value.parse::<u32>().unwrap());
+        info!("sliding for clahe tile grid size: {}", tile_grid_size.get());
+    };
+    let clip_limit_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        clip_limit.set(value.parse::<f64>().unwrap());
+        info!("sliding for clahe clip limit: {}", clip_limit.get());
+    };
+
+    view! {
+        <div class="flex flex-col">
+            <label for="clahe-tile-grid-size-slider" class="some-custom-css">
+                "tile grid "
+                {tile_grid_size}
+                " × "
+                {tile_grid_size}
+            </label>
+            <input
+                id="clahe-tile-grid-size-slider"
+                class="range"
+                type="range"
+                name="clahe-tile-grid-size"
+                min="1"
+                max="16"
+                step="1"
+                prop:value=move || tile_grid_size.get().to_string()
+                on:input=preview(previewing, tile_grid_size_slider)
+                on:change=commit(previewing, tile_grid_size_slider)
+            />
+            <label for="clahe-clip-limit-slider" class="some-custom-css">
+                "clip limit "
+                {clip_limit}
+            </label>
+            <input
+                id="clahe-clip-limit-slider"
+                class="range"
+                type="range"
+                name="clahe-clip-limit"
+                min="1"
+                max="10"
+                step="0.5"
+                prop:value=move || clip_limit.get().to_string()
+                on:input=preview(previewing, clip_limit_slider)
+                on:change=commit(previewing, clip_limit_slider)
+            />
+        </div>
+    }
+}
+
+#[component]
+pub fn Threshold(
+    method: RwSignal<ThresholdMethod>,
+    level: RwSignal<u32>,
+    block_size: RwSignal<u32>,
+    offset: RwSignal<f64>,
+    otsu_level: Signal<Option<u8>>,
+    previewing: RwSignal<bool>,
+) -> impl IntoView {
+    let select_method = move |ev: Event| {
+        let value = event_target_value(&ev);
+        method.set(match value.as_str() {
+            "manual" => ThresholdMethod::Manual,
+            "adaptive-mean" => ThresholdMethod::AdaptiveMean,
+            "adaptive-gaussian" => ThresholdMethod::AdaptiveGaussian,
+            _ => ThresholdMethod::Otsu,
+        });
+    };
+    let level_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        level.set(value.parse::<u32>().unwrap());
+        info!("sliding for threshold level: {}", level.get());
+    };
+    let block_size_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        block_size.set(value.parse::<u32>().unwrap());
+        info!("sliding for threshold block size: {}", block_size.get());
+    };
+    let offset_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        offset.set(value.parse::<f64>().unwrap());
+        info!("sliding for threshold offset: {}", offset.get());
+    };
+
+    // every method only shows the inputs it uses, otsu picks its level in the worker
+    let method_inputs = move || match method.get() {
+        ThresholdMethod::Manual => view! {
+            <label for="threshold-level-slider" class="some-custom-css">
+                "level "
+                {level}
+            </label>
+            <input
+                id="threshold-level-slider"
+                class="range"
+                type="range"
+                name="threshold-level"
+                min="0"
+                max="255"
+                step="1"
+                prop:value=move || level.get().to_string()
+                on:input=preview(previewing, level_slider)
+                on:change=commit(previewing, level_slider)
+            />
+        }
+        .into_view(),
+        ThresholdMethod::Otsu => view! {
+            <span class="some-custom-css" title="the level otsu's method picked for this step">
+                "level "
+                {move || otsu_level.get().map_or("…".to_string(), |level| level.to_string())}
+            </span>
+        }
+        .into_view(),
+        ThresholdMethod::AdaptiveMean | ThresholdMethod::AdaptiveGaussian => view! {
+            <label for="threshold-block-size-slider" class="some-custom-css">
+                "block size "
+                {block_size}
+            </label>
+            <input
+                id="threshold-block-size-slider"
+                class="range"
+                type="range"
+                name="threshold-block-size"
+                min="3"
+                max="51"
+                step="2"
+                prop:value=move || block_size.get().to_string()
+                on:input=preview(previewing, block_size_slider)
+                on:change=commit(previewing, block_size_slider)
+            />
+            <label for="threshold-offset-slider" class="some-custom-css">
+                "offset "
+                {offset}
+            </label>
+            <input
+                id="threshold-offset-slider"
+                class="range"
+                type="range"
+                name="threshold-offset"
+                min="-20"
+                max="20"
+                step="1"
+                prop:value=move || offset.get().to_string()
+                on:input=preview(previewing, offset_slider)
+                on:change=commit(previewing, offset_slider)
+            />
+        }
+        .into_view(),
+    };
+
+    view! {
+        <div class="flex flex-col gap-1">
+            <select class="select select-sm" on:change=select_method>
+                <option value="manual" selected=move || method.get() == ThresholdMethod::Manual>
+                    "manual"
+                </option>
+                <option value="otsu" selected=move || method.get() == ThresholdMethod::Otsu>
+                    "otsu"
+                </option>
+                <option
+                    value="adaptive-mean"
+                    selected=move || method.get() == ThresholdMethod::AdaptiveMean
+                >
+                    "adaptive mean"
+                </option>
+                <option
+                    value="adaptive-gaussian"
+                    selected=move || method.get() == ThresholdMethod::AdaptiveGaussian
+                >
+                    "adaptive gaussian"
+                </option>
+            </select>
+            {method_inputs}
+        </div>
+    }
+}
+
+#[component]
+pub fn FrequencySpectrum(phase: RwSignal<bool>) -> impl IntoView {
+    let toggle_phase = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        phase.set(element.checked());
+    };
+
+    view! {
+        <label class="flex flex-row gap-2 items-center">
+            <input
+                type="checkbox"
+                class="checkbox"
+                prop:checked=move || phase.get()
+                on:change=toggle_phase
+            />
+            "show phase instead of magnitude"
+        </label>
+    }
+}
+
+#[component]
+pub fn FrequencyFilter(
+    band: RwSignal<FrequencyBand>,
+    shape: RwSignal<FilterShape>,
+    cutoff: RwSignal<f64>,
+    bandwidth: RwSignal<f64>,
+    order: RwSignal<u32>,
+    previewing: RwSignal<bool>,
+) -> impl IntoView {
+    let select_band = move |ev: Event| {
+        let value = event_target_value(&ev);
+        band.set(match value.as_str() {
+            "high-pass" => FrequencyBand::HighPass,
+            "band-pass" => FrequencyBand::BandPass,
+            _ => FrequencyBand::LowPass,
+        });
+    };
+    let select_shape = move |ev: Event| {
+        let value = event_target_value(&ev);
+        shape.set(match value.as_str() {
+            "ideal" => FilterShape::Ideal,
+            "butterworth" => FilterShape::Butterworth,
+            _ => FilterShape::Gaussian,
+        });
+    };
+    let cutoff_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        cutoff.set(value.parse::<f64>().unwrap());
+        info!("sliding for frequency filter cutoff: {}", cutoff.get());
+    };
+    let bandwidth_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        bandwidth.set(value.parse::<f64>().unwrap());
+        info!(
+            "sliding for frequency filter bandwidth: {}",
+            bandwidth.get()
+        );
+    };
+    let order_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        order.set(value.parse::<u32>().unwrap());
+        info!("sliding for frequency filter order: {}", order.get());
+    };
+
+    // bandwidth only matters for band-pass filters and order only for butterworth filters
+    let bandwidth_input = move || {
+        (band.get() == FrequencyBand::BandPass).then(|| {
+            view! {
+                <label for="frequency-filter-bandwidth-slider" class="some-custom-css">
+                    "bandwidth "
+                    {bandwidth}
+                </label>
+                <input
+                    id="frequency-filter-bandwidth-slider"
+                    class="range"
+                    type="range"
+                    name="frequency-filter-bandwidth"
+                    min="0.01"
+                    max="0.5"
+                    step="0.01"
+                    prop:value=move || bandwidth.get().to_string()
+                    on:input=preview(previewing, bandwidth_slider)
+                    on:change=commit(previewing, bandwidth_slider)
+                />
+            }
+        })
+    };
+    let order_input = move || {
+        (shape.get() == FilterShape::Butterworth).then(|| {
+            view! {
+                <label for="frequency-filter-order-slider" class="some-custom-css">
+                    "order "
+                    {order}
+                </label>
+                <input
+                    id="frequency-filter-order-slider"
+                    class="range"
+                    type="range"
+                    name="frequency-filter-order"
+                    min="1"
+                    max="10"
+                    step="1"
+                    prop:value=move || order.get().to_string()
+                    on:input=preview(previewing, order_slider)
+                    on:change=commit(previewing, order_slider)
+                />
+            }
+        })
+    };
+
+    view! {
+        <div class="flex flex-col gap-1">
+            <div class="flex flex-row gap-2">
+                <select class="select select-sm" on:change=select_band>
+                    <option value="low-pass" selected=move || band.get() == FrequencyBand::LowPass>
+                        "low-pass"
+                    </option>
+                    <option
+                        value="high-pass"
+                        selected=move || band.get() == FrequencyBand::HighPass
+                    >
+                        "high-pass"
+                    </option>
+                    <option
+                        value="band-pass"
+                        selected=move || band.get() == FrequencyBand::BandPass
+                    >
+                        "band-pass"
+                    </option>
+                </select>
+                <select class="select select-sm" on:change=select_shape>
+                    <option value="ideal" selected=move || shape.get() == FilterShape::Ideal>
+                        "ideal"
+                    </option>
+                    <option
+                        value="butterworth"
+                        selected=move || shape.get() == FilterShape::Butterworth
+                    >
+                        "butterworth"
+                    </option>
+                    <option value="gaussian" selected=move || shape.get() == FilterShape::Gaussian>
+                        "gaussian"
+                    </option>
+                </select>
+            </div>
+            <label for="frequency-filter-cutoff-slider" class="some-custom-css">
+                "cutoff "
+                {cutoff}
+            </label>
+            <input
+                id="frequency-filter-cutoff-slider"
+                class="range"
+                type="range"
+                name="frequency-filter-cutoff"
+                min="0.01"
+                max="1"
+                step="0.01"
+                prop:value=move || cutoff.get().to_string()
+                on:input=preview(previewing, cutoff_slider)
+                on:change=commit(previewing, cutoff_slider)
+            />
+            {bandwidth_input}
+            {order_input}
+        </div>
+    }
+}
+
+#[component]
+pub fn CornerDetection(
+    method: RwSignal<CornerMethod>,
+    k: RwSignal<f64>,
+    window_size: RwSignal<u32>,
+    max_corners: RwSignal<u32>,
+    min_distance: RwSignal<u32>,
+    previewing: RwSignal<bool>,
+) -> impl IntoView {
+    let select_method = move |ev: Event| {
+        let value = event_target_value(&ev);
+        method.set(match value.as_str() {
+            "shi-tomasi" => CornerMethod::ShiTomasi,
+            _ => CornerMethod::Harris,
+        });
+    };
+    let k_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        k.set(value.parse::<f64>().unwrap());
+        info!("sliding for corner detection sensitivity: {}", k.get());
+    };
+    let window_size_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        window_size.set(value.parse::<u32>().unwrap());
+        info!(
+            "sliding for corner detection window size: {}",
+            window_size.get()
+        );
+    };
+    let max_corners_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        max_corners.set(value.parse::<u32>().unwrap());
+        info!(
+            "sliding for corner detection max corners: {}",
+            max_corners.get()
+        );
+    };
+    let min_distance_slider = move |ev: Event| {
+        let element = ev.target().unwrap().dyn_into::<HtmlInputElement>().unwrap();
+        let value = element.value();
+        min_distance.set(value.parse::<u32>().unwrap());
+        info!(
+            "sliding for corner detection min distance: {}",
+            min_distance.get()
+        );
+    };
+
+    // shi-tomasi uses the smaller eigenvalue directly so it has no sensitivity
+    let k_input = move || {
+        (method.get() == CornerMethod::Harris).then(|| {
+            view! {
+                <label for="corner-k-slider" class="some-custom-css">
+                    "sensitivity "
+                    {k}
+                </label>
+                <input
+                    id="corner-k-slider"
+                    class="range"
+                    type="range"
+                    name="corner-k"
+                    min="0.01"
+                    max="0.2"
+                    step="0.01"
+                    prop:value=move || k.get().to_string()
+                    on:input=preview(previewing, k_slider)
+                    on:change=commit(previewing, k_slider)
+                />
+            }
+        })
+    };
+
+    view! {
+        <div class="flex flex-col gap-1">
+            <select class="select select-sm" on:change=select_method>
+                <option value="harris" selected=move || method.get() == CornerMethod::Harris>
+                    "harris"
+                </option>
+                <option
+                    value="shi-tomasi"
+                    selected=move || method.get() == CornerMethod::ShiTomasi
+                >
+                    "shi-tomasi"
+                </option>
+            </select>
+            {k_input}
+            <label for="corner-window-size-slider" class="some-custom-css">
+                "window size "
+                {window_size}
+            </label>
+            <input
+                id="corner-window-size-slider"
+                class="range"
+                type="range"
+                name="corner-window-size"
+                min="3"
+                max="15"
+                step="2"
+                prop:value=move || window_size.get().to_string()
+                on:input=preview(previewing, window_size_slider)
+                on:change=commit(previewing, window_size_slider)
+            />
+            <label for="corner-max-corners-slider" class="some-custom-css">
+                "max corners "
+                {max_corners}
+            </label>
+            <input
+                id="corner-max-corners-slider"
+                class="range"
+                type="range"
+                name="corner-max-corners"
+                min="1"
+                max="500"
+                step="1"
+                prop:value=move || max_corners.get().to_string()
+                on:input=preview(previewing, max_corners_slider)
+                on:change=commit(previewing, max_corners_slider)
+            />
+            <label for="corner-min-distance-slider" class="some-custom-css">
+                "min distance "
+                {min_distance}
+            </label>
+            <input
+                id="corner-min-distance-slider"
+                class="range"
+                type="range"
+                name="corner-min-distance"
+                min="1"
+                max="50"
+                step="1"
+                prop:value=move || min_distance.get().to_string()
+                on:input=preview(previewing, min_distance_slider)
+                on:change=commit(previewing, min_distance_slider)
+            />
+        </div>
+    }
+}
+
+#[component]
+pub fn InvisibleSelectFile<F>(file_input_ref: NodeRef<Input>, on_file: F) -> impl IntoView
+where
+    F: Fn(File) + 'static,
+{
     let on_change = move |_ev| {
         let node = file_input_ref.get().unwrap();
-        let files = node.files().unwrap();
-        let file = files.item(0).unwrap();
-        let image_url_raw = Url::create_object_url_with_blob(&file).unwrap();
-        set_image_url.set(image_url_raw);
+        if let Some(file) = node.files().and_then(|files| files.item(0)) {
+            on_file(file);
+        }
+        // cleared so picking the same file again still loads it
+        node.set_value("");
     };
 
     view! {
         <input
             type="file"
             id="file-input"
-            accept="image/png, image/jpeg"
+            accept="image/png, image/jpeg, image/webp, image/bmp, image/tiff, image/gif, .tga, .tif, .tiff"
             style="display: none;"
             _ref=file_input_ref
             on:change=on_change
@@ -238,22 +981,161 @@ pub fn InvisibleSelectFile(
 
 #[component]
 pub fn CurrentAlgorithm(
-    gamma: RwSignal<f64>,
-    invert: RwSignal<bool>,
-    box_blur_amount: RwSignal<u32>,
-    sobel_edge_detector_threshold: RwSignal<u32>,
-    algorithm: ReadSignal<Option<Algorithm>>,
+    pipeline: Pipeline,
+    previewing: RwSignal<bool>,
+    otsu_levels: RwSignal<Vec<(u32, u8)>>,
 ) -> impl IntoView {
-    let current_algorithm = move || match algorithm.get() {
-        Some(current_algorithm) => match current_algorithm {
-            Algorithm::Gamma => Some(view! { <Gamma gamma=gamma/> }),
-            Algorithm::Invert => Some(view! { <Invert invert=invert/> }),
-            Algorithm::BoxBlur => Some(view! { <BoxBlur box_blur_amount=box_blur_amount/> }),
-            Algorithm::SobelEdgeDetector => {
-                Some(view! { <SobelEdgeDetector threshold=sobel_edge_detector_threshold/> })
-            }
-        },
-        None => None,
+    let current_algorithm = move || {
+        pipeline
+            .selected_step()
+            .map(|step| match step.parameters() {
+                StepParameters::Gamma(gamma) => {
+                    view! { <Gamma gamma=gamma previewing=previewing/> }
+                }
+                StepParameters::Invert => view! { <Invert/> },
+                StepParameters::BoxBlur(box_blur_amount) => {
+                    view! { <BoxBlur box_blur_amount=box_blur_amount previewing=previewing/> }
+                }
+                StepParameters::SobelEdgeDetector(threshold) => {
+                    view! { <SobelEdgeDetector threshold=threshold previewing=previewing/> }
+                }
+                StepParameters::GaussianBlur(sigma) => {
+                    view! { <GaussianBlur sigma=sigma previewing=previewing/> }
+                }
+                StepParameters::CannyEdgeDetector {
+                    sigma,
+                    low_threshold,
+                    high_threshold,
+                } => {
+                    view! {
+                        <CannyEdgeDetector
+                            sigma=sigma
+                            low_threshold=low_threshold
+                            high_threshold=high_threshold
+                            previewing=previewing
+                        />
+                    }
+                }
+                StepParameters::UnsharpMask {
+                    amount,
+                    radius,
+                    threshold,
+                } => {
+                    view! {
+                        <UnsharpMask
+                            amount=amount
+                            radius=radius
+                            threshold=threshold
+                            previewing=previewing
+                        />
+                    }
+                }
+                StepParameters::Exposure {
+                    stops,
+                    highlight_rolloff,
+                } => {
+                    view! {
+                        <Exposure
+                            stops=stops
+                            highlight_rolloff=highlight_rolloff
+                            previewing=previewing
+                        />
+                    }
+                }
+                StepParameters::FrequencySpectrum { phase } => {
+                    view! { <FrequencySpectrum phase=phase/> }
+                }
+                StepParameters::FrequencyFilter {
+                    band,
+                    shape,
+                    cutoff,
+                    bandwidth,
+                    order,
+                } => {
+                    view! {
+                        <FrequencyFilter
+                            band=band
+                            shape=shape
+                            cutoff=cutoff
+                            bandwidth=bandwidth
+                            order=order
+                            previewing=previewing
+                        />
+                    }
+                }
+                StepParameters::CornerDetection {
+                    method,
+                    k,
+                    window_size,
+                    max_corners,
+                    min_distance,
+                } => {
+                    view! {
+                        <CornerDetection
+                            method=method
+                            k=k
+                            window_size=window_size
+                            max_corners=max_corners
+                            min_distance=min_distance
+                            previewing=previewing
+                        />
+                    }
+                }
+                StepParameters::HueSaturationLightness {
+                    hue,
+                    saturation,
+                    lightness,
+                } => {
+                    view! {
+                        <HueSaturationLightness
+                            hue=hue
+                            saturation=saturation
+                            lightness=lightness
+                            previewing=previewing
+                        />
+                    }
+                }
+                StepParameters::HistogramEqualization => view! { <HistogramEqualization/> },
+                StepParameters::Clahe {
+                    tile_grid_size,
+                    clip_limit,
+                } => {
+                    view! {
+                        <Clahe
+                            tile_grid_size=tile_grid_size
+                            clip_limit=clip_limit
+                            previewing=previewing
+                        />
+                    }
+                }
+                StepParameters::Threshold {
+                    method,
+                    level,
+                    block_size,
+                    offset,
+                } => {
+                    let id = step.id();
+                    let otsu_level = Signal::derive(move || {
+                        let index = pipeline.operation_index(id)? as u32;
+                        otsu_levels.with(|levels| {
+                            levels
+                                .iter()
+                                .find(|(step_index, _)| *step_index == index)
+                                .map(|(_, level)| *level)
+                        })
+                    });
+                    view! {
+                        <Threshold
+                            method=method
+                            level=level
+                            block_size=block_size
+                            offset=offset
+                            otsu_level=otsu_level
+                            previewing=previewing
+                        />
+                    }
+                }
+            })
     };
     view! { <div>{current_algorithm}</div> }
 }